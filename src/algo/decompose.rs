@@ -0,0 +1,125 @@
+use std::{fs::File, io::{BufWriter, Write}, path::PathBuf};
+
+use ndarray::Array;
+
+use crate::{cost_metric::{count_stacks, CostMetric, Metric}, helper::{format_batch, format_batch_extras_with_carry, format_cost_vector}, model::item_set::{find_item, item_crate_bounds, min_crates_from, output_legend_file}, options::read_json_order, Batch, CostVec, OrderNum, QueueVec, ARGS, CARRY, CRATE_LIMITS, ITEM_SET_CATEGORY_ORDER, ITEM_SET_NAME, JSON_OPTIONS, MATERIAL_COUNT, OUTPUT_PATH, TRUCK_SIZE_U16};
+
+// Splits an order list into a sequence of batches that satisfy the metric
+// Each batch is the largest (most crates, then fewest stacks) group that fits in the remaining order
+// Whatever cannot be placed in a batch satisfying the metric is reported as the remainder
+// Only the first batch goes in the truck with the carried over materials
+pub fn decompose_order_with_metric(order_path: &PathBuf, metric: &dyn Metric) {
+    let (batches, remainder) = decompose_order(order_path, metric, &CARRY);
+    let no_carry = CostVec::zeros((1, *MATERIAL_COUNT));
+    let remainder_string = if remainder.iter().all(|q| q.iter().all(|x| *x == 0)) { String::from("None") } else { format_batch(&remainder) };
+
+    let mut res = String::new();
+    for (i, (batch, cost)) in batches.iter().enumerate() {
+        let carry = if i == 0 { &*CARRY } else { &no_carry };
+        res += &format!("Batch {}: {}\nCost    : {}{}\n", i + 1, format_batch(batch), format_cost_vector(cost), format_batch_extras_with_carry(batch, cost, carry));
+    }
+    res += &format!("Remainder: {}", remainder_string);
+    println!("{res}");
+
+    if ARGS.output {
+        let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
+        let file_str = format!("decompose_with_{}_{}.txt", metric.file_str(), output_suffix);
+        let mut f = BufWriter::new(File::create(OUTPUT_PATH.join(&file_str)).unwrap());
+        let _ = writeln!(f, "{res}");
+        if !ARGS.output_batch_long { output_legend_file(); }
+    }
+}
+
+// Splits an order list into batches that satisfy the metric, the first batch is seeded with the carried over materials
+// Returns (Vec<(batch, cost)>, remainder), the cost of the first batch includes the carry
+pub fn decompose_order(order_path: &PathBuf, metric: &dyn Metric, carry: &CostVec) -> (Vec<(Batch, CostVec)>, Batch) {
+    // Remaining crates per item for all categories
+    let mut demand: Vec<Vec<OrderNum>> = ITEM_SET_CATEGORY_ORDER.iter().map(|c| vec![0; usize::from(c.size())]).collect();
+    for (name, count) in read_json_order(order_path) {
+        let (category, item) = find_item(&name).unwrap_or_else(|| panic!("Order: [{name}] is not an item in {}.", *ITEM_SET_NAME));
+        demand[category][item] += count;
+    }
+
+    // Base valid queues for all categories
+    let base_queues: Vec<Vec<(QueueVec, CostVec, u16)>> = ITEM_SET_CATEGORY_ORDER.iter().map(|c| c.generate_valid_queue_vec()).collect();
+
+    let mut batches: Vec<(Batch, CostVec)> = Vec::new();
+    let no_carry = CostVec::zeros((1, *MATERIAL_COUNT));
+    while let Some((batch, cost)) = find_largest_batch(&base_queues, &demand, metric, if batches.is_empty() { carry } else { &no_carry }) {
+        for (category, queue) in batch.iter().enumerate() {
+            for (item, q) in queue.iter().enumerate() {
+                demand[category][item] -= q;
+            }
+        }
+        batches.push((batch, cost));
+    }
+
+    let remainder: Batch = demand.iter().map(|d| Array::from_shape_vec((1, d.len()), d.clone()).unwrap()).collect();
+    return (batches, remainder);
+}
+
+// Finds the non-empty batch with the most crates (ties broken by fewest stacks) that fits within demand and satisfies the metric
+// The search is seeded with whatever is carried over in the truck
+fn find_largest_batch(base_queues: &[Vec<(QueueVec, CostVec, u16)>], demand: &[Vec<OrderNum>], metric: &dyn Metric, carry: &CostVec) -> Option<(Batch, CostVec)> {
+    // Only keep queues that do not order more than what is left
+    let queues: Vec<Vec<&(QueueVec, CostVec, u16)>> = base_queues.iter().zip(demand)
+                                                                 .map(|(qs, d)| qs.iter().filter(|(q, _, _)| q.iter().zip(d).all(|(x, y)| x <= y)).collect())
+                                                                 .collect();
+
+    // Crates the item minimums still need after every category
+    let min_crates = min_crates_from(&item_crate_bounds(JSON_OPTIONS.item_crates.as_ref()));
+
+    // Most crates each category (and all categories after it) can still add, used to prune the search
+    let mut remaining_max: Vec<u16> = queues.iter().map(|qs| qs.iter().map(|(_, _, count)| *count).max().unwrap_or(0)).collect();
+    remaining_max.push(0);
+    for i in (0..queues.len()).rev() {
+        remaining_max[i] += remaining_max[i + 1];
+    }
+
+    let mut best: Option<(Batch, CostVec, u16, u16)> = None;
+
+    // Stack for DFS: Vec<(batch, cost, item_count)>
+    let mut stack: Vec<(Batch, CostVec, u16)> = Vec::new();
+    for (queue, cost, item_count) in queues.first()?.iter() {
+        let new_cost = cost + carry;
+        if CostMetric::Affordable.satisfies_metric(&new_cost) && item_count + min_crates[1] <= TRUCK_SIZE_U16 && item_count + remaining_max[1] >= CRATE_LIMITS.min_crates {
+            stack.push((vec![queue.clone()], new_cost, *item_count));
+        }
+    }
+
+    while let Some((cur_batch, cur_cost, cur_item_count)) = stack.pop() {
+        // A batch can not beat the best batch if it can not reach as many crates
+        if let Some((_, _, best_item_count, _)) = best && cur_item_count + remaining_max[cur_batch.len()] < best_item_count { continue; }
+
+        if cur_batch.len() == queues.len() {
+            if cur_item_count == 0 || cur_item_count < CRATE_LIMITS.min_crates || !metric.satisfies(&cur_cost) { continue; }
+
+            let stack_count = count_stacks(&cur_cost);
+            let is_better = match best {
+                Some((_, _, best_item_count, best_stack_count)) => (cur_item_count, std::cmp::Reverse(stack_count)) > (best_item_count, std::cmp::Reverse(best_stack_count)),
+                None => true,
+            };
+            if is_better { best = Some((cur_batch, cur_cost, cur_item_count, stack_count)); }
+            continue;
+        }
+
+        // For all valid queues in the next category
+        for (next_queue, next_cost, next_item_count) in queues[cur_batch.len()].iter() {
+            let new_cost = cur_cost.clone() + next_cost;
+            let new_item_count = cur_item_count + next_item_count;
+
+            // If the new batch is affordable, the number of items, with the items the minimums still need, < TRUCK_SIZE_U16
+            // and the remaining categories can still reach the fewest crates of a batch, push to stack
+            if CostMetric::Affordable.satisfies_metric(&new_cost) && new_item_count + min_crates[cur_batch.len() + 1] <= TRUCK_SIZE_U16
+               && new_item_count + remaining_max[cur_batch.len() + 1] >= CRATE_LIMITS.min_crates {
+                let mut new_batch = cur_batch.clone();
+                new_batch.push(next_queue.clone());
+                if metric.can_still_satisfy(&new_batch, &new_cost, new_item_count, queues.len() - new_batch.len()) {
+                    stack.push((new_batch, new_cost, new_item_count));
+                }
+            }
+        }
+    }
+
+    return best.map(|(batch, cost, _, _)| (batch, cost));
+}
@@ -0,0 +1,89 @@
+use std::{fs::File, io::{BufWriter, Write}};
+
+use crate::{cost_metric::{CostMetric, Metric}, helper::{format_batch, format_batch_extras, format_cost_vector}, model::{crate_limits::suffix_sums, item_set::{check_item_minimums_searched, item_crate_bounds, min_crates_from, output_legend_file}}, Batch, CostNum, CostVec, QueueVec, ARGS, CARRY, CRATE_LIMITS, ITEM_SET_CATEGORY_ORDER, JSON_OPTIONS, MATERIAL_COUNT, MATERIAL_ORDER, OUTPUT_PATH, TRUCK_SIZE_U16};
+
+// Finds every batch of the first n categories that satisfies the metric and whose cost is within tolerance of the target for every material
+// Branches are pruned once a material is over the target, or the remaining categories can no longer reach it
+pub fn find_n_exact_cost_batches(n: usize, metric: &dyn Metric, target: &[CostNum], tolerance: &[CostNum]) {
+    if n < 1 || n > ITEM_SET_CATEGORY_ORDER.len() { panic!("n must be between 1 and {}, was provided {n}", ITEM_SET_CATEGORY_ORDER.len()); }
+    let bounds = item_crate_bounds(JSON_OPTIONS.item_crates.as_ref());
+    check_item_minimums_searched(&bounds, n);
+    CRATE_LIMITS.check_searched(n);
+    let min_crates = min_crates_from(&bounds);
+    let material_names: Vec<&str> = MATERIAL_ORDER.iter().map(|m| m.short_name()).collect();
+    if target.len() != *MATERIAL_COUNT {
+        panic!("Exact cost: expected {} amounts ({}), was provided {}.", *MATERIAL_COUNT, material_names.join(", "), target.len());
+    }
+    // No tolerance is an exact match, a single tolerance applies to every material
+    let tolerance: Vec<CostNum> = match tolerance.len() {
+        0 => vec![0; *MATERIAL_COUNT],
+        1 => vec![tolerance[0]; *MATERIAL_COUNT],
+        l if l == *MATERIAL_COUNT => tolerance.to_vec(),
+        l => panic!("Tolerance: expected 1 or {} amounts ({}), was provided {l}.", *MATERIAL_COUNT, material_names.join(", ")),
+    };
+    let lower: Vec<CostNum> = target.iter().zip(tolerance.iter()).map(|(t, d)| t.saturating_sub(*d)).collect();
+    let upper: Vec<CostNum> = target.iter().zip(tolerance.iter()).map(|(t, d)| t.saturating_add(*d)).collect();
+
+    // Base valid queues for the searched categories
+    let base_queues: Vec<Vec<(QueueVec, CostVec, u16)>> = ITEM_SET_CATEGORY_ORDER.iter().take(n).map(|c| {
+                                                                                               let mut res = c.generate_valid_queue_vec();
+                                                                                               res.reverse();
+                                                                                               return res;
+                                                                                             }).collect();
+    // Most crates every category onwards can still add, for --min-crates
+    let max_crates = suffix_sums(&base_queues.iter().map(|qs| qs.iter().map(|(_, _, count)| *count).max().unwrap_or(0)).collect::<Vec<u16>>());
+
+    // most_remaining[i] is the most of every material categories i.. can still add
+    let mut most_remaining: Vec<Vec<CostNum>> = vec![vec![0; *MATERIAL_COUNT]; n + 1];
+    for i in (0..n).rev() {
+        for idx in 0..*MATERIAL_COUNT {
+            let most = base_queues[i].iter().map(|(_, c, _)| c[[0, idx]]).max().unwrap_or(0);
+            most_remaining[i][idx] = most_remaining[i + 1][idx].saturating_add(most);
+        }
+    }
+    let reachable = |cost: &CostVec, next: usize| -> bool {
+        return cost.iter().enumerate().all(|(idx, x)| *x <= upper[idx] && x.saturating_add(most_remaining[next][idx]) >= lower[idx]);
+    };
+
+    let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
+    let target_str: Vec<String> = target.iter().map(|x| x.to_string()).collect();
+    let file_str = format!("{n}_batches_costing_{}_with_{}_{}.txt", target_str.join("_"), metric.file_str(), output_suffix);
+    let mut output = if ARGS.output { Some(BufWriter::new(File::create(OUTPUT_PATH.join(&file_str)).unwrap())) } else { None };
+    if ARGS.output && !ARGS.output_batch_long { output_legend_file(); }
+
+    // Stack for DFS: Vec<(batch, cost, item_count)>, seeded with whatever is carried over in the truck
+    let mut stack: Vec<(Batch, CostVec, u16)> = vec![(Vec::new(), CARRY.clone(), 0)];
+    let mut batch_count: u64 = 0;
+    while let Some((cur_batch, cur_cost, cur_item_count)) = stack.pop() {
+        // Exit conditions
+        // If every category was searched, the cost is within tolerance of the target, output if it satisfies the metric
+        if cur_batch.len() == n {
+            if cur_item_count == 0 || !metric.satisfies(&cur_cost) { continue; }
+            let res = format!("Batch: {}\nCost : {}{}", format_batch(&cur_batch), format_cost_vector(&cur_cost), format_batch_extras(&cur_batch, &cur_cost));
+            match output {
+                Some(ref mut f) => { let _ = writeln!(f, "{res}"); },
+                None => println!("{res}"),
+            }
+            batch_count += 1;
+            continue;
+        }
+
+        // Add children to continue search
+        for (next_queue, next_cost, next_item_count) in &base_queues[cur_batch.len()] {
+            let new_cost = cur_cost.clone() + next_cost;
+            let new_item_count = cur_item_count + next_item_count;
+
+            // If the new batch is affordable, the target can still be reached, the number of items, with the items the minimums still need, <= TRUCK_SIZE_U16
+            // and the remaining categories can still reach the fewest crates of a batch, push to stack
+            if CostMetric::Affordable.satisfies_metric(&new_cost) && new_item_count + min_crates[cur_batch.len() + 1] <= TRUCK_SIZE_U16 && new_item_count + max_crates[cur_batch.len() + 1] >= CRATE_LIMITS.min_crates
+               && reachable(&new_cost, cur_batch.len() + 1) {
+                let mut new_batch = cur_batch.clone();
+                new_batch.push(next_queue.clone());
+                if metric.can_still_satisfy(&new_batch, &new_cost, new_item_count, n - new_batch.len()) {
+                    stack.push((new_batch, new_cost, new_item_count));
+                }
+            }
+        }
+    }
+    println!("Found {batch_count} batches satisfying {metric} costing {}", format_cost_vector(&CostVec::from_shape_vec((1, target.len()), target.to_vec()).unwrap()));
+}
@@ -0,0 +1,35 @@
+use std::{fs::File, io::{BufWriter, Write}};
+
+use crate::{algo::n_batches::visit_n_batches_with_metric, cost_metric::Metric, helper::{format_batch, format_batch_extras, format_cost_vector}, model::{item_set::output_legend_file, production::{item_production_times, require_category_production_times, QueueSimulation, Seconds}}, Batch, CostVec, ARGS, CARRY, OUTPUT_PATH};
+
+// Finds the `top` batches of the first n categories that satisfy the metric with the shortest turnaround time
+// Batches with the same turnaround keep the order find_n_batches_with_metric visits them in
+pub fn find_n_fastest_batches_with_metric(n: usize, metric: &dyn Metric, top: usize) {
+    if top < 1 { panic!("top must be >= 1, was provided {top}"); }
+    require_category_production_times(n, "fastest");
+    let times = item_production_times();
+
+    // Fastest batches so far, sorted by ascending turnaround
+    let mut best: Vec<(Batch, CostVec, Seconds)> = Vec::new();
+    visit_n_batches_with_metric(n, metric, &Vec::new(), &CARRY, |batch, cost| {
+        let turnaround = QueueSimulation::new(batch, &times).turnaround;
+        if best.len() == top && turnaround >= best.last().unwrap().2 { return; }
+
+        let idx = best.partition_point(|(_, _, t)| *t <= turnaround);
+        best.insert(idx, (batch.clone(), cost.clone(), turnaround));
+        best.truncate(top);
+    });
+
+    let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
+    let file_str = format!("{n}_fastest_batches_with_{}_{}.txt", metric.file_str(), output_suffix);
+    let mut output = if ARGS.output { Some(BufWriter::new(File::create(OUTPUT_PATH.join(&file_str)).unwrap())) } else { None };
+    if ARGS.output && !ARGS.output_batch_long { output_legend_file(); }
+
+    for (batch, cost, turnaround) in best {
+        let res = format!("Batch: {}\nCost : {}\nReady: {}s{}", format_batch(&batch), format_cost_vector(&cost), turnaround, format_batch_extras(&batch, &cost));
+        match output {
+            Some(ref mut f) => { let _ = writeln!(f, "{res}"); },
+            None => println!("{res}"),
+        }
+    }
+}
@@ -1,3 +1,13 @@
+pub mod decompose;
+pub mod exact;
+pub mod fastest;
 pub mod n_batches;
 pub mod n_groups;
+pub mod near_miss;
+pub mod pareto;
+pub mod plan;
+pub mod prioritize;
+pub mod sample;
+pub mod schedule;
+pub mod session;
 pub mod n_prime_groups;
@@ -1,42 +1,120 @@
 use std::{fs::File, io::{BufWriter, Write}};
 
-use crate::{cost_metric::CostMetric, helper::{format_batch_long, format_batch_short, format_cost_vector}, model::item_set::{material_grouped_warden_categories::MaterialGroupedWardenCategories, output_legend_file, warden_categories::WardenCategories, ItemSetCategory, ItemSetOption}, Batch, CostVec, QueueVec, ARGS, CATEGORY_COUNT, ITEM_SET_CATEGORY_ORDER, OUTPUT_PATH, TRUCK_SIZE_U16};
+use crate::{cost_metric::{CostMetric, Metric}, helper::{batch_cost, format_batch, format_batch_long, format_batch_short, format_batch_extras, format_cost_vector}, model::{crate_limits::suffix_sums, item_set::{check_item_minimums_searched, item_crate_bounds, min_crates_from, output_legend_file}}, Batch, CostVec, QueueVec, ARGS, CARRY, CRATE_LIMITS, ITEM_SET_CATEGORY_ORDER, JSON_OPTIONS, OUTPUT_PATH, TRUCK_SIZE_U16};
 
-pub fn find_all_batches_with_metric(metric: CostMetric) {
-    find_n_batches_with_metric(CATEGORY_COUNT, metric);
+pub fn find_all_batches_with_metric(metric: &dyn Metric) {
+    find_n_batches_with_metric(ITEM_SET_CATEGORY_ORDER.len(), metric);
 }
 
 
-pub fn find_n_batches_with_metric(n: usize, metric: CostMetric) {
-    // Crash if n < 1  
+pub fn find_n_batches_with_metric(n: usize, metric: &dyn Metric) {
+    let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
+    let file_str = format!("{n}_batches_with_{}_{}.txt", metric.file_str(), output_suffix);
+    let output_path = OUTPUT_PATH.join(&file_str);
+
+    let mut output = if ARGS.output { Some(BufWriter::new(File::create(output_path).unwrap())) } else { None };
+    if ARGS.output && !ARGS.output_batch_long { output_legend_file(); }
+
+    let empty_batch: Batch = Vec::new();
+    visit_n_batches_ranked_by_metric(n, metric, &empty_batch, &CARRY, |batch, cost| {
+        if let Some(ref mut f) = output {
+            let batch_string = if ARGS.output_batch_long { format_batch_long(batch) } else { format_batch_short(batch) };
+            let _ = writeln!(f, "Batch: {}\nCost : {}{}", batch_string, format_cost_vector(cost), format_batch_extras(batch, cost));
+        }
+    });
+}
+
+// Finds all batches of the first n categories that contain the partial batch and satisfy the metric
+pub fn complete_n_batches_with_metric(n: usize, metric: &dyn Metric, partial: &Batch) {
+    let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
+    let file_str = format!("{n}_batches_completing_{}_with_{}_{}.txt", format_batch_short(partial).replace(' ', "_"), metric.file_str(), output_suffix);
+    let output_path = OUTPUT_PATH.join(&file_str);
+
+    let mut output = if ARGS.output { Some(BufWriter::new(File::create(output_path).unwrap())) } else { None };
+    if ARGS.output && !ARGS.output_batch_long { output_legend_file(); }
+
+    let mut completion_count: u64 = 0;
+    visit_n_batches_ranked_by_metric(n, metric, partial, &CARRY, |batch, cost| {
+        let batch_string = format!("Batch: {}\nCost : {}{}", format_batch(batch), format_cost_vector(cost), format_batch_extras(batch, cost));
+        match output {
+            Some(ref mut f) => { let _ = writeln!(f, "{batch_string}"); },
+            None => println!("{batch_string}"),
+        }
+        completion_count += 1;
+    });
+    println!("Found {completion_count} completions of {}", format_batch_short(partial));
+}
+
+// Same as visit_n_batches_with_metric, but ranked metrics visit the batches with the smallest rank first
+pub fn visit_n_batches_ranked_by_metric(n: usize, metric: &dyn Metric, partial: &Batch, carry: &CostVec, mut on_batch: impl FnMut(&Batch, &CostVec)) {
+    if !metric.is_ranked() {
+        visit_n_batches_with_metric(n, metric, partial, carry, on_batch);
+        return;
+    }
+
+    let mut batches: Vec<(Batch, CostVec, f64)> = Vec::new();
+    visit_n_batches_with_metric(n, metric, partial, carry, |batch, cost| batches.push((batch.clone(), cost.clone(), metric.rank(cost).unwrap_or(f64::INFINITY))));
+    batches.sort_by(|a, b| a.2.total_cmp(&b.2));
+    for (batch, cost, _) in batches {
+        on_batch(&batch, &cost);
+    }
+}
+
+// DFS over the first n categories seeded with the cost and item count of the partial batch
+// Every category queue must order at least what the partial batch orders
+// The carry is what is already in the truck, it is part of the cost the metric checks and on_batch gets
+pub fn visit_n_batches_with_metric(n: usize, metric: &dyn Metric, partial: &Batch, carry: &CostVec, mut on_batch: impl FnMut(&Batch, &CostVec)) {
+    // Crash if n < 1
     if n < 1 { panic!("n must be >= 1, was provided {n}"); }
+    if let Some(c) = partial.iter().skip(n).position(|q| q.iter().any(|x| *x != 0)) {
+        panic!("Partial batch orders from category {}, only the first {n} categories are searched.", c + n);
+    }
+    // Crates the item minimums still need after every category
+    let bounds = item_crate_bounds(JSON_OPTIONS.item_crates.as_ref());
+    check_item_minimums_searched(&bounds, n);
+    CRATE_LIMITS.check_searched(n);
+    let min_crates = min_crates_from(&bounds);
 
-    // Base valid queues for all categories
-    let base_queues: Vec<Vec<(QueueVec, CostVec, u16)>> = ITEM_SET_CATEGORY_ORDER.iter().map(|c|  { let mut res = c.generate_valid_queue_vec();
+    // Base valid queues for all categories, as (queue, cost and item count added on top of the partial queue)
+    let base_queues: Vec<Vec<(QueueVec, CostVec, u16)>> = ITEM_SET_CATEGORY_ORDER.iter().enumerate().map(|(i, c)|  {
+                                                                                               let mut res = c.generate_valid_queue_vec();
+                                                                                               if let Some(partial_queue) = partial.get(i) {
+                                                                                                   let partial_cost = c.queue_cost(partial_queue);
+                                                                                                   let partial_item_count = partial_queue.sum();
+                                                                                                   res = res.into_iter()
+                                                                                                            .filter(|(q, _, _)| q.iter().zip(partial_queue).all(|(x, y)| x >= y))
+                                                                                                            .map(|(q, cost, count)| (q, cost - &partial_cost, count - partial_item_count))
+                                                                                                            .collect();
+                                                                                               }
                                                                                                res.reverse();
                                                                                                return res;
                                                                                              }).collect();
+    // Most crates every searched category onwards can still add, for --min-crates
+    let max_crates = suffix_sums(&base_queues.iter().take(n).map(|qs| qs.iter().map(|(_, _, count)| *count).max().unwrap_or(0)).collect::<Vec<u16>>());
+
+    // Seed with the partial batch and whatever is carried over in the truck
+    let start_cost: CostVec = batch_cost(partial) + carry;
+    let start_item_count: u16 = partial.iter().map(|q| q.sum()).sum();
 
     // Stack for DFS: Vec<(batch, cost, item_count)>
     let mut stack: Vec<(Batch, CostVec, u16)> = Vec::new();
     for (queue, cost, item_count) in base_queues.first().unwrap().clone() {
-        stack.push((vec![queue], cost, item_count));
+        let new_cost = start_cost.clone() + cost;
+        let new_item_count = start_item_count + item_count;
+        if CostMetric::Affordable.satisfies_metric(&new_cost) && new_item_count + min_crates[1] <= TRUCK_SIZE_U16 && new_item_count + max_crates[1] >= CRATE_LIMITS.min_crates {
+            let new_batch = vec![queue];
+            if metric.can_still_satisfy(&new_batch, &new_cost, new_item_count, n - 1) {
+                stack.push((new_batch, new_cost, new_item_count));
+            }
+        }
     }
 
-    let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
-    let file_str = format!("{n}_batches_with_{}_{}.txt", metric, output_suffix);
-    let output_path = OUTPUT_PATH.join(&file_str);
-
-    let mut output = if ARGS.output { Some(BufWriter::new(File::create(output_path).unwrap())) } else { None };
-    if ARGS.output && !ARGS.output_batch_long { output_legend_file(); }
-
     while let Some((cur_batch, cur_cost, cur_item_count)) = stack.pop() {
         // Exit conditions
-        // If batch is length n and satisfied the metric, output to file
+        // If batch is length n and satisfied the metric, output
         if cur_batch.len() == n {
-            if let Some(ref mut f) = output && metric.satisfies_metric(&cur_cost) {
-                let batch_string = if ARGS.output_batch_long { format_batch_long(&cur_batch) } else { format_batch_short(&cur_batch) };
-                let _ = writeln!(f, "Batch: {}\nCost : {}", batch_string, format_cost_vector(&cur_cost));
+            if metric.satisfies(&cur_cost) {
+                on_batch(&cur_batch, &cur_cost);
             }
             continue;
         }
@@ -48,13 +126,43 @@ pub fn find_n_batches_with_metric(n: usize, metric: CostMetric) {
                 let new_cost = cur_cost.clone() + next_cost;
                 let new_item_count = cur_item_count + next_item_count;
 
-                // If the new batch is affordable and the number of items < TRUCK_SIZE_U16, push to stack
-                if CostMetric::Affordable.satisfies_metric(&new_cost) && new_item_count <= TRUCK_SIZE_U16 {
+                // If the new batch is affordable, the number of items, with the items the minimums still need, < TRUCK_SIZE_U16
+                // and the remaining categories can still reach the fewest crates of a batch, push to stack
+                if CostMetric::Affordable.satisfies_metric(&new_cost) && new_item_count + min_crates[cur_batch.len() + 1] <= TRUCK_SIZE_U16
+                   && new_item_count + max_crates[cur_batch.len() + 1] >= CRATE_LIMITS.min_crates {
                     let mut new_batch = cur_batch.clone();
                     new_batch.push(next_queue.clone());
-                    stack.push((new_batch, new_cost, new_item_count));
+                    if metric.can_still_satisfy(&new_batch, &new_cost, new_item_count, n - new_batch.len()) {
+                        stack.push((new_batch, new_cost, new_item_count));
+                    }
                 }
             }
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::set_test_args;
+
+    #[test]
+    fn carry_is_added_once_to_every_batch() {
+        set_test_args();
+        let carry = CostVec::from_shape_vec((1, 4), vec![60, 0, 0, 0]).unwrap();
+        let mut expected: Vec<Batch> = Vec::new();
+        visit_n_batches_with_metric(1, &CostMetric::Affordable, &Vec::new(), &CostVec::zeros((1, 4)), |batch, cost| {
+            let carried = cost + &carry;
+            if CostMetric::Affordable.satisfies(&carried) && CostMetric::Stackable.satisfies(&carried) { expected.push(batch.clone()); }
+        });
+
+        let mut visited: Vec<Batch> = Vec::new();
+        visit_n_batches_with_metric(1, &CostMetric::Stackable, &Vec::new(), &carry, |batch, cost| {
+            assert_eq!(*cost, batch_cost(batch) + &carry, "cost of {}", format_batch_short(batch));
+            visited.push(batch.clone());
+        });
+        assert!(!visited.is_empty());
+        assert_eq!(visited.len(), expected.len());
+        assert!(visited.iter().all(|b| expected.contains(b)));
+    }
 }
\ No newline at end of file
@@ -1,16 +1,16 @@
 use std::{fs::File, path::PathBuf};
 use std::io::{BufWriter, Write};
 
-use crate::ITEM_SET_CATEGORY_ORDER;
-use crate::{cost_metric::CostMetric, helper::{format_batch_long, format_batch_short, format_cost_vector},
-            model::item_set::{material_grouped_warden_categories::MaterialGroupedWardenCategories, output_legend_file, warden_categories::WardenCategories, ItemSetCategory, ItemSetOption},
-            ARGS, OUTPUT_PATH, Batch, CostVec, QueueVec, CATEGORY_COUNT, TRUCK_SIZE_U16};
+use crate::{CARRY, CRATE_LIMITS, ITEM_SET_CATEGORY_ORDER};
+use crate::{cost_metric::{CostMetric, Metric}, helper::{format_batch_long, format_batch_short, format_batch_extras, format_cost_vector},
+            model::{crate_limits::suffix_sums, item_set::output_legend_file},
+            ARGS, OUTPUT_PATH, Batch, CostVec, QueueVec, TRUCK_SIZE_U16};
 
-pub fn find_all_groups_with_metric(metric: CostMetric) {
-    find_n_groups_with_metric(CATEGORY_COUNT, metric);
+pub fn find_all_groups_with_metric(metric: &dyn Metric) {
+    find_n_groups_with_metric(ITEM_SET_CATEGORY_ORDER.len(), metric);
 }
 
-pub fn find_n_groups_with_metric(n: usize, metric: CostMetric) {
+pub fn find_n_groups_with_metric(n: usize, metric: &dyn Metric) {
     // Crash if n < 1  
     if n < 1 { panic!("n must be >= 1, was provided {n}"); }
     
@@ -19,17 +19,25 @@ pub fn find_n_groups_with_metric(n: usize, metric: CostMetric) {
                                                                                                res.reverse();
                                                                                                return res;
                                                                                              }).collect();
+    // Most crates every category onwards can still add, for --min-crates
+    let max_crates = suffix_sums(&base_queues.iter().map(|qs| qs.iter().map(|(_, _, count)| *count).max().unwrap_or(0)).collect::<Vec<u16>>());
 
     // Stack for DFS: Vec<(batch, cost, item_count, non_zero_queue_count)>
     let mut stack: Vec<(Batch, CostVec, u16, u8)> = Vec::new();
     for (queue, cost, item_count) in base_queues.first().unwrap().clone() {
         // Check if non-zero queue
         let non_zero_queue = if queue.iter().all(|x| *x == 0) { 0 } else { 1 };
-        stack.push((vec![queue], cost, item_count, non_zero_queue));
+        let new_cost = cost + &*CARRY;
+        if CostMetric::Affordable.satisfies_metric(&new_cost) && item_count + max_crates[1] >= CRATE_LIMITS.min_crates {
+            let new_batch = vec![queue];
+            if metric.can_still_satisfy(&new_batch, &new_cost, item_count, base_queues.len() - 1) {
+                stack.push((new_batch, new_cost, item_count, non_zero_queue));
+            }
+        }
     }
 
     let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
-    let file_str = format!("{n}_groups_with_{}_{}.txt", metric, output_suffix);
+    let file_str = format!("{n}_groups_with_{}_{}.txt", metric.file_str(), output_suffix);
     let output_path: PathBuf = OUTPUT_PATH.join(&file_str);
 
     let mut output = if ARGS.output { Some(BufWriter::new(File::create(output_path).unwrap())) } else { None };
@@ -37,12 +45,12 @@ pub fn find_n_groups_with_metric(n: usize, metric: CostMetric) {
 
     while let Some((cur_batch, cur_cost, cur_item_count, cur_non_zero_queue_count)) = stack.pop() {
         // Exit conditions
-        // If group has n non-zero queues and satisfies metric, output to file
+        // If group has n non-zero queues, the fewest crates of a batch and satisfies metric, output to file
         if usize::from(cur_non_zero_queue_count) == n {
-            if metric.satisfies_metric(&cur_cost) &&
+            if cur_item_count >= CRATE_LIMITS.min_crates && metric.satisfies(&cur_cost) &&
                let Some(ref mut f) = output {
                 let batch_string = if ARGS.output_batch_long { format_batch_long(&cur_batch) } else { format_batch_short(&cur_batch) };
-                let _ = writeln!(f, "Batch: {}\nCost : {}", batch_string, format_cost_vector(&cur_cost));
+                let _ = writeln!(f, "Batch: {}\nCost : {}{}", batch_string, format_cost_vector(&cur_cost), format_batch_extras(&cur_batch, &cur_cost));
             }
             continue;
         }
@@ -57,11 +65,13 @@ pub fn find_n_groups_with_metric(n: usize, metric: CostMetric) {
                 let non_zero_queue = if next_queue.iter().all(|x| *x == 0) { 0 } else { 1 };
                 let new_non_zero_queue_count = cur_non_zero_queue_count + non_zero_queue; 
 
-                // If the new group is affordable and the number of items < TRUCK_SIZE_U16, push to stack
-                if CostMetric::Affordable.satisfies_metric(&new_cost) && new_item_count <= TRUCK_SIZE_U16 {
+                // If the new group is affordable, the number of items < TRUCK_SIZE_U16 and the remaining categories can still reach the fewest crates of a batch, push to stack
+                if CostMetric::Affordable.satisfies_metric(&new_cost) && new_item_count <= TRUCK_SIZE_U16 && new_item_count + max_crates[cur_batch.len() + 1] >= CRATE_LIMITS.min_crates {
                     let mut new_batch = cur_batch.clone();
                     new_batch.push(next_queue.clone());
-                    stack.push((new_batch, new_cost, new_item_count, new_non_zero_queue_count));
+                    if metric.can_still_satisfy(&new_batch, &new_cost, new_item_count, base_queues.len() - new_batch.len()) {
+                        stack.push((new_batch, new_cost, new_item_count, new_non_zero_queue_count));
+                    }
                 }
             }
         }
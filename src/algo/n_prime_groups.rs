@@ -1,22 +1,12 @@
 use std::{fs::File, io::{BufWriter, Write}, path::PathBuf};
 
-use crate::{cost_metric::{count_stacks, CostMetric}, helper::{format_batch_groups, format_batch_long, format_batch_short, format_cost_vector}, model::item_set::{material_grouped_warden_categories::MaterialGroupedWardenCategories, output_legend_file, warden_categories::WardenCategories, ItemSetCategory, ItemSetOption}, Batch, CostVec, QueueVec, ARGS, CATEGORY_COUNT, ITEM_SET_CATEGORY_ORDER, OUTPUT_PATH, TRUCK_SIZE, TRUCK_SIZE_U16};
+use crate::{cost_metric::{count_stacks, CostMetric, Metric}, helper::{format_batch_groups, format_batch_long, format_batch_short, format_batch_extras, format_cost_vector}, model::{crate_limits::suffix_sums, item_set::output_legend_file}, Batch, CostVec, QueueVec, ARGS, CARRY, CRATE_LIMITS, CATEGORY_COUNT, ITEM_SET_CATEGORY_ORDER, OUTPUT_PATH, TRUCK_SIZE, TRUCK_SIZE_U16};
 
-pub fn find_all_prime_groups_with_metric(metric: CostMetric) {
-    match ARGS.item_set {
-        ItemSetOption::Warden                => _find_prime_n_groups_with_metric::<WardenCategories>(CATEGORY_COUNT, metric),
-        ItemSetOption::MaterialGroupedWarden => _find_prime_n_groups_with_metric::<MaterialGroupedWardenCategories>(CATEGORY_COUNT, metric),
-    };
+pub fn find_all_prime_groups_with_metric(metric: &dyn Metric) {
+    find_prime_n_groups_with_metric(ITEM_SET_CATEGORY_ORDER.len(), metric);
 }
 
-pub fn find_prime_n_groups_with_metric(n:usize, metric: CostMetric) {
-    match ARGS.item_set {
-        ItemSetOption::Warden                => _find_prime_n_groups_with_metric::<WardenCategories>(n, metric),
-        ItemSetOption::MaterialGroupedWarden => _find_prime_n_groups_with_metric::<MaterialGroupedWardenCategories>(n, metric),
-    };
-}
-
-fn _find_prime_n_groups_with_metric<S: ItemSetCategory>(n: usize, metric: CostMetric) {
+pub fn find_prime_n_groups_with_metric(n: usize, metric: &dyn Metric) {
     // Crash if n < 1  
     if n < 1 { panic!("n must be >= 1, was provided {n}"); }
     
@@ -25,13 +15,21 @@ fn _find_prime_n_groups_with_metric<S: ItemSetCategory>(n: usize, metric: CostMe
                                                                                                res.reverse();
                                                                                                return res;
                                                                                              }).collect();
+    // Most crates every category onwards can still add, for --min-crates
+    let max_crates = suffix_sums(&base_queues.iter().map(|qs| qs.iter().map(|(_, _, count)| *count).max().unwrap_or(0)).collect::<Vec<u16>>());
 
     // Stack for DFS: Vec<(batch, cost, item_count, non_zero_queue_count)>
     let mut stack: Vec<(Batch, CostVec, u16, u8)> = Vec::new();
     for (queue, cost, item_count) in base_queues.first().unwrap().clone() {
         // Check if non-zero queue
         let non_zero_queue = if queue.iter().all(|x| *x == 0) { 0 } else { 1 };
-        stack.push((vec![queue], cost, item_count, non_zero_queue));
+        let new_cost = cost + &*CARRY;
+        if CostMetric::Affordable.satisfies_metric(&new_cost) && item_count + max_crates[1] >= CRATE_LIMITS.min_crates {
+            let new_batch = vec![queue];
+            if metric.can_still_satisfy(&new_batch, &new_cost, item_count, base_queues.len() - 1) {
+                stack.push((new_batch, new_cost, item_count, non_zero_queue));
+            }
+        }
     }
 
     let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
@@ -41,8 +39,8 @@ fn _find_prime_n_groups_with_metric<S: ItemSetCategory>(n: usize, metric: CostMe
     while let Some((cur_batch, cur_cost, cur_item_count, cur_non_zero_queue_count)) = stack.pop() {
         // Exit conditions 
         if cur_non_zero_queue_count == 0 { continue; }
-        // If batch has n non-zero queues and satisfies metric, output to file
-        if metric.satisfies_metric(&cur_cost) {
+        // If batch has the fewest crates of a batch and satisfies metric, output to file
+        if cur_item_count >= CRATE_LIMITS.min_crates && metric.satisfies(&cur_cost) {
             if ARGS.output {
                 let stack_count =  count_stacks(&cur_cost);
                 let file_str = format!("prime_{}_groups_{}_stacks_{}.txt", cur_non_zero_queue_count, stack_count, output_suffix);
@@ -53,7 +51,7 @@ fn _find_prime_n_groups_with_metric<S: ItemSetCategory>(n: usize, metric: CostMe
 
                 let f = outputs[idx].get_or_insert(BufWriter::new(File::create(output_path).unwrap()));
 
-                let _ = writeln!(f, "Batch : {}\nCost  : {}\nGroups: {}", batch_string, format_cost_vector(&cur_cost), format_batch_groups(&cur_batch));
+                let _ = writeln!(f, "Batch : {}\nCost  : {}\nGroups: {}{}", batch_string, format_cost_vector(&cur_cost), format_batch_groups(&cur_batch), format_batch_extras(&cur_batch, &cur_cost));
             }
             continue;
         }
@@ -70,11 +68,13 @@ fn _find_prime_n_groups_with_metric<S: ItemSetCategory>(n: usize, metric: CostMe
                 let non_zero_queue = if next_queue.iter().all(|x| *x == 0) { 0 } else { 1 };
                 let new_non_zero_queue_count = cur_non_zero_queue_count + non_zero_queue; 
 
-                // If the new group is affordable and the number of items < TRUCK_SIZE_U16, push to stack
-                if CostMetric::Affordable.satisfies_metric(&new_cost) && new_item_count <= TRUCK_SIZE_U16 {
+                // If the new group is affordable, the number of items < TRUCK_SIZE_U16 and the remaining categories can still reach the fewest crates of a batch, push to stack
+                if CostMetric::Affordable.satisfies_metric(&new_cost) && new_item_count <= TRUCK_SIZE_U16 && new_item_count + max_crates[cur_batch.len() + 1] >= CRATE_LIMITS.min_crates {
                     let mut new_batch = cur_batch.clone();
                     new_batch.push(next_queue.clone());
-                    stack.push((new_batch, new_cost, new_item_count, new_non_zero_queue_count));
+                    if metric.can_still_satisfy(&new_batch, &new_cost, new_item_count, base_queues.len() - new_batch.len()) {
+                        stack.push((new_batch, new_cost, new_item_count, new_non_zero_queue_count));
+                    }
                 }
             }
         }
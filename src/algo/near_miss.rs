@@ -0,0 +1,82 @@
+use std::{collections::HashSet, fs::File, io::{BufWriter, Write}};
+
+
+use crate::{algo::n_batches::visit_n_batches_with_metric, cost_metric::{CostMetric, Metric}, helper::{format_batch, format_batch_extras, format_cost_vector, format_item}, model::item_set::output_legend_file, CostNum, CostVec, ARGS, CARRY, ITEM_SET_CATEGORY_ORDER, MATERIAL_ORDER, OUTPUT_PATH, TRUCK_SIZE_U16};
+
+// Distance of every material to its closest stack boundary
+pub fn stack_boundary_distance(cv: &CostVec) -> Vec<CostNum> {
+    return cv.iter().enumerate().map(|(idx, x)| {
+        let stack_value = MATERIAL_ORDER[idx].stack_value();
+        let r = x % stack_value;
+        r.min(stack_value - r)
+    }).collect();
+}
+
+// Finds batches of the first n categories that do not satisfy the metric but are within tolerance of a stack boundary for every material
+// Each near miss lists the single item additions and removals that make it satisfy the metric, smallest change first
+pub fn find_n_near_misses_with_metric(n: usize, metric: &dyn Metric, tolerance: CostNum) {
+    let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
+    let file_str = format!("{n}_near_misses_{tolerance}_with_{}_{}.txt", metric.file_str(), output_suffix);
+    let output_path = OUTPUT_PATH.join(&file_str);
+
+    let mut output = if ARGS.output { Some(BufWriter::new(File::create(output_path).unwrap())) } else { None };
+    if ARGS.output && !ARGS.output_batch_long { output_legend_file(); }
+
+    // Valid queues per category, a fix must keep the changed queue valid
+    let valid_queues: Vec<HashSet<Vec<u16>>> = ITEM_SET_CATEGORY_ORDER.iter()
+                                                                     .map(|c| c.generate_valid_queue_vec().iter().map(|(q, _, _)| q.iter().copied().collect()).collect())
+                                                                     .collect();
+
+    let mut near_miss_count: u64 = 0;
+    visit_n_batches_with_metric(n, &CostMetric::Affordable, &Vec::new(), &CARRY, |batch, cost| {
+        if metric.satisfies(cost) || stack_boundary_distance(cost).iter().any(|d| *d > tolerance) { return; }
+
+        let item_count: u16 = batch.iter().map(|q| q.sum()).sum();
+
+        // (change in materials, +1 / -1, category, item, new cost)
+        let mut fixes: Vec<(u32, char, usize, usize, CostVec)> = Vec::new();
+        for (category, queue) in batch.iter().enumerate() {
+            for item in 0..queue.len() {
+                // Queue costs are recomputed since facility discounts make an extra crate cost depend on the order size
+                let queue_cost = ITEM_SET_CATEGORY_ORDER[category].queue_cost(queue);
+
+                // Addition
+                let mut new_queue = queue.clone();
+                new_queue[[0, item]] += 1;
+                if item_count < TRUCK_SIZE_U16 && valid_queues[category].contains(&new_queue.iter().copied().collect::<Vec<u16>>()) {
+                    let item_cost = ITEM_SET_CATEGORY_ORDER[category].queue_cost(&new_queue) - &queue_cost;
+                    let new_cost = cost.clone() + &item_cost;
+                    if metric.satisfies(&new_cost) {
+                        fixes.push((item_cost.iter().map(|x| u32::from(*x)).sum(), '+', category, item, new_cost));
+                    }
+                }
+
+                // Removal
+                if queue[[0, item]] > 0 {
+                    let mut new_queue = queue.clone();
+                    new_queue[[0, item]] -= 1;
+                    if valid_queues[category].contains(&new_queue.iter().copied().collect::<Vec<u16>>()) {
+                        let item_cost = queue_cost.clone() - &ITEM_SET_CATEGORY_ORDER[category].queue_cost(&new_queue);
+                        let new_cost = cost.clone() - &item_cost;
+                        if metric.satisfies(&new_cost) {
+                            fixes.push((item_cost.iter().map(|x| u32::from(*x)).sum(), '-', category, item, new_cost));
+                        }
+                    }
+                }
+            }
+        }
+        fixes.sort_by_key(|(change, _, _, _, _)| *change);
+
+        let mut res = format!("Batch: {}\nCost : {}\nOff  : {}{}", format_batch(batch), format_cost_vector(cost), stack_boundary_distance(cost).iter().map(|d| d.to_string()).collect::<Vec<_>>().join(" "), format_batch_extras(batch, cost));
+        for (_, sign, category, item, new_cost) in fixes {
+            res += &format!("\nFix  : {sign}{} -> {}", format_item(category, item), format_cost_vector(&new_cost));
+        }
+
+        match output {
+            Some(ref mut f) => { let _ = writeln!(f, "{res}"); },
+            None => println!("{res}"),
+        }
+        near_miss_count += 1;
+    });
+    println!("Found {near_miss_count} near misses within {tolerance} of a stack boundary");
+}
@@ -0,0 +1,187 @@
+use std::{fmt, fs::File, io::{BufWriter, Write}, str::FromStr};
+
+use crate::{algo::n_batches::visit_n_batches_with_metric, cost_metric::{count_stacks, Metric}, helper::{format_batch, format_batch_extras, format_cost_vector}, model::{item_set::output_legend_file, material::Material}, Batch, CostVec, ARGS, CARRY, ITEM_SET_NAME, MATERIAL_ORDER, OUTPUT_PATH};
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Objective {
+    MinStacks,
+    MaxStacks,
+    MinCrates,
+    MaxCrates,
+    MinMaterial(Material),
+    MinCategories,
+    MaxCategories,
+}
+
+impl Objective {
+    // Value of the objective for a batch, lower is always better
+    pub fn value(&self, batch: &Batch, cv: &CostVec) -> i64 {
+        let material = |m: &Material| i64::from(cv[[0, MATERIAL_ORDER.iter().position(|x| x == m).unwrap()]]);
+        let crates = || i64::from(batch.iter().map(|q| q.sum()).sum::<u16>());
+        let categories = || batch.iter().filter(|q| q.iter().any(|x| *x != 0)).count() as i64;
+
+        return match self {
+            Self::MinStacks => i64::from(count_stacks(cv)),
+            Self::MaxStacks => -i64::from(count_stacks(cv)),
+            Self::MinCrates => crates(),
+            Self::MaxCrates => -crates(),
+            Self::MinMaterial(m) => material(m),
+            Self::MinCategories => categories(),
+            Self::MaxCategories => -categories(),
+        };
+    }
+
+    // Value of the objective as the user reads it
+    pub fn display_value(&self, value: i64) -> i64 {
+        return match self {
+            Self::MaxStacks | Self::MaxCrates | Self::MaxCategories => -value,
+            _ => value,
+        };
+    }
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MinMaterial(m) => write!(f, "Min{}{}", m.short_name()[..1].to_uppercase(), &m.short_name()[1..]),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// Parses kebab case objectives, e.g. "min-stacks" or "min-<material>" for any material such as "min-bmat"
+impl FromStr for Objective {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        return match s.as_str() {
+            "min-stacks" => Ok(Self::MinStacks),
+            "max-stacks" => Ok(Self::MaxStacks),
+            "min-crates" => Ok(Self::MinCrates),
+            "max-crates" => Ok(Self::MaxCrates),
+            "min-categories" => Ok(Self::MinCategories),
+            "max-categories" => Ok(Self::MaxCategories),
+            _ => s.strip_prefix("min-")
+                  .and_then(Material::from_short_name)
+                  .map(Self::MinMaterial)
+                  .ok_or(format!("Objective [{s}] is not a valid objective.")),
+        };
+    }
+}
+
+// Fewest candidates before the fronts are reassigned during the search
+const MIN_REASSIGN_LEN: usize = 1024;
+
+// a dominates b if it is no worse in every objective and better in at least one
+fn dominates(a: &[i64], b: &[i64]) -> bool {
+    return a.iter().zip(b).all(|(x, y)| x <= y) && a.iter().zip(b).any(|(x, y)| x < y);
+}
+
+// Finds the first `fronts` Pareto fronts of batches of the first n categories that satisfy the metric
+pub fn find_n_pareto_fronts_with_metric(n: usize, metric: &dyn Metric, objectives: &[Objective], fronts: usize) {
+    if objectives.is_empty() { panic!("At least one objective must be provided."); }
+    if fronts < 1 { panic!("fronts must be >= 1, was provided {fronts}"); }
+    for objective in objectives {
+        if let Objective::MinMaterial(m) = objective && !MATERIAL_ORDER.contains(m) {
+            panic!("Objective [{objective}]: {} is not a material of {}.", m.short_name(), *ITEM_SET_NAME);
+        }
+    }
+
+    // Vec<(batch, cost, objective values, front)>
+    // Fronts are only reassigned once the candidates doubled, until then they are lower bounds since later batches can only push a batch back
+    let mut candidates: Vec<(Batch, CostVec, Vec<i64>, usize)> = Vec::new();
+    let mut assigned_len: usize = 0;
+    visit_n_batches_with_metric(n, metric, &Vec::new(), &CARRY, |batch, cost| {
+        let values: Vec<i64> = objectives.iter().map(|o| o.value(batch, cost)).collect();
+
+        // Front of the new batch is at least one past the furthest front dominating it
+        let front = candidates.iter()
+                              .filter(|(_, _, v, _)| dominates(v, &values))
+                              .map(|(_, _, _, f)| f + 1)
+                              .max().unwrap_or(0);
+        if front >= fronts { return; }
+
+        candidates.push((batch.clone(), cost.clone(), values, front));
+        if candidates.len() >= 2 * assigned_len.max(MIN_REASSIGN_LEN) {
+            candidates = assign_fronts(std::mem::take(&mut candidates), fronts);
+            assigned_len = candidates.len();
+        }
+    });
+    let mut candidates = assign_fronts(candidates, fronts);
+
+    let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
+    let objective_str = objectives.iter().map(|o| o.to_string()).collect::<Vec<_>>().join("_");
+    let file_str = format!("{n}_batches_pareto_{}_with_{}_{}.txt", objective_str, metric.file_str(), output_suffix);
+    let mut output = if ARGS.output { Some(BufWriter::new(File::create(OUTPUT_PATH.join(&file_str)).unwrap())) } else { None };
+    if ARGS.output && !ARGS.output_batch_long { output_legend_file(); }
+
+    candidates.sort_by(|a, b| (a.3, &a.2).cmp(&(b.3, &b.2)));
+    for front in 0..fronts {
+        let members: Vec<&(Batch, CostVec, Vec<i64>, usize)> = candidates.iter().filter(|c| c.3 == front).collect();
+        if members.is_empty() { break; }
+
+        let mut res = format!("Front {}: {} batches", front + 1, members.len());
+        for (batch, cost, values, _) in members {
+            let values_str = objectives.iter().zip(values).map(|(o, v)| format!("{}={}", o, o.display_value(*v))).collect::<Vec<_>>().join(" ");
+            res += &format!("\nBatch : {}\nCost  : {}\nValues: {}{}", format_batch(batch), format_cost_vector(cost), values_str, format_batch_extras(batch, cost));
+        }
+
+        match output {
+            Some(ref mut f) => { let _ = writeln!(f, "{res}"); },
+            None => println!("{res}"),
+        }
+    }
+}
+
+// Sorts candidates into fronts, dropping anything past the last front
+// Every batch sorts after the batches dominating it, so each batch goes in the first front where no member dominates it
+fn assign_fronts(mut candidates: Vec<(Batch, CostVec, Vec<i64>, usize)>, fronts: usize) -> Vec<(Batch, CostVec, Vec<i64>, usize)> {
+    candidates.sort_by(|a, b| a.2.cmp(&b.2));
+
+    let mut res: Vec<(Batch, CostVec, Vec<i64>, usize)> = Vec::new();
+    // Indices into res of the members of every front
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); fronts];
+    for (batch, cost, values, _) in candidates {
+        let Some(front) = members.iter().position(|m| !m.iter().any(|i| dominates(&res[*i].2, &values))) else { continue; };
+        members[front].push(res.len());
+        res.push((batch, cost, values, front));
+    }
+    return res;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fronts_of(values: &[Vec<i64>], fronts: usize) -> Vec<(Vec<i64>, usize)> {
+        let candidates = values.iter().map(|v| (Vec::new(), CostVec::zeros((1, 1)), v.clone(), 0)).collect();
+        let mut res: Vec<(Vec<i64>, usize)> = assign_fronts(candidates, fronts).into_iter().map(|(_, _, v, f)| (v, f)).collect();
+        res.sort();
+        return res;
+    }
+
+    #[test]
+    fn assigns_every_batch_to_the_first_front_not_dominating_it() {
+        let values = vec![vec![3, 3], vec![1, 5], vec![6, 6], vec![5, 1], vec![4, 4], vec![2, 2], vec![2, 2]];
+        assert_eq!(fronts_of(&values, 3), vec![(vec![1, 5], 0), (vec![2, 2], 0), (vec![2, 2], 0), (vec![3, 3], 1), (vec![4, 4], 2), (vec![5, 1], 0)]);
+    }
+
+    #[test]
+    fn matches_peeling_off_the_non_dominated_batches() {
+        // Deterministic spread of values over 3 objectives
+        let mut x: i64 = 7;
+        let values: Vec<Vec<i64>> = (0..300).map(|_| (0..3).map(|_| { x = (x * 1103515245 + 12345) % 2147483648; x % 20 }).collect()).collect();
+
+        let fronts = 4;
+        let mut expected: Vec<(Vec<i64>, usize)> = Vec::new();
+        let mut rest = values.clone();
+        for front in 0..fronts {
+            let (first, others): (Vec<Vec<i64>>, Vec<Vec<i64>>) = rest.iter().cloned().partition(|v| !rest.iter().any(|w| dominates(w, v)));
+            expected.extend(first.into_iter().map(|v| (v, front)));
+            rest = others;
+        }
+        expected.sort();
+        assert_eq!(fronts_of(&values, fronts), expected);
+    }
+}
@@ -0,0 +1,139 @@
+use std::{fs::File, io::{BufWriter, Write}, path::PathBuf};
+
+use crate::{algo::{n_batches::visit_n_batches_with_metric, prioritize::item_weights}, cost_metric::Metric, helper::{format_batch, format_batch_extras_with_carry, format_cost_vector}, model::{income::Income, item_set::output_legend_file, production::{item_production_times, require_category_production_times, QueueSimulation, Seconds}}, Batch, CostVec, ARGS, CARRY, ITEM_SET_CATEGORY_ORDER, MATERIAL_ORDER, OUTPUT_PATH};
+
+// A batch of a shift plan
+pub struct PlannedBatch {
+    pub batch: Batch,
+    // Cost the metric was checked against, the batch and whatever was carried over in the truck
+    pub cost: CostVec,
+    // Materials already in the truck before the batch
+    pub carry: CostVec,
+    pub start: Seconds,
+    pub ready: Seconds,
+    pub weight: f64,
+}
+
+impl PlannedBatch {
+    // Materials taken from the stock for the batch
+    pub fn paid(&self) -> CostVec {
+        return &self.cost - &self.carry;
+    }
+}
+
+// Plans a shift of batches of the first n categories that satisfy the metric, produced one after the other
+// Needs production times for every item of the first n categories, see plan_shift
+pub fn plan_shift_with_metric(n: usize, metric: &dyn Metric, income: &Income, hours: f64, weights_path: Option<&PathBuf>) {
+    if hours <= 0.0 { panic!("hours must be > 0, was provided {hours}"); }
+    let horizon = (hours * 3600.0) as Seconds;
+
+    let weights: Vec<Vec<f64>> = match weights_path {
+        Some(path) => item_weights(path),
+        None => ITEM_SET_CATEGORY_ORDER.iter().map(|c| vec![1.0; usize::from(c.size())]).collect(),
+    };
+    require_category_production_times(n, "plan");
+    let plan = plan_shift(n, metric, income, horizon, &weights, &item_production_times(), &CARRY);
+
+    let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
+    let file_str = format!("{n}_batches_shift_{hours}h_with_{}_{}.txt", metric.file_str(), output_suffix);
+    let mut output = if ARGS.output { Some(BufWriter::new(File::create(OUTPUT_PATH.join(&file_str)).unwrap())) } else { None };
+    if ARGS.output && !ARGS.output_batch_long { output_legend_file(); }
+
+    let mut res = String::new();
+    for p in plan.iter() {
+        res += &format!("Batch: {}\nCost : {}\nStart: {}s\nReady: {}s{}\n", format_batch(&p.batch), format_cost_vector(&p.cost), p.start, p.ready, format_batch_extras_with_carry(&p.batch, &p.cost, &p.carry));
+    }
+    let stock = income.available(horizon, &spent(&plan)).iter().zip(MATERIAL_ORDER.iter()).map(|(x, m)| format!("{x} {}", m.short_name())).collect::<Vec<_>>().join(", ");
+    res += &format!("Batches: {}\nWeight : {}\nStock  : {}", plan.len(), plan.iter().map(|p| p.weight).sum::<f64>(), stock);
+
+    match output {
+        Some(ref mut f) => { let _ = writeln!(f, "{res}"); },
+        None => println!("{res}"),
+    }
+}
+
+// Plans batches of the first n categories that satisfy the metric and are ready within the horizon, produced one after the other
+// Every step queues the batch with the most weight per second it takes from the end of the previous batch, including any wait for income
+// Batches are only started once every material they cost is on hand, so no material ever runs negative
+// Only the first batch goes in the truck with the carried over materials, its cost includes them and they are not paid for again
+// Every later batch satisfies the metric and is paid for on its own
+pub fn plan_shift(n: usize, metric: &dyn Metric, income: &Income, horizon: Seconds, weights: &[Vec<f64>], times: &[Vec<Seconds>], carry: &CostVec) -> Vec<PlannedBatch> {
+    // Vec<(batch, cost including the carry, turnaround, weight)>
+    let candidates_with = |carry: &CostVec| -> Vec<(Batch, CostVec, Seconds, f64)> {
+        let mut res: Vec<(Batch, CostVec, Seconds, f64)> = Vec::new();
+        visit_n_batches_with_metric(n, metric, &Vec::new(), carry, |batch, cost| {
+            let weight: f64 = batch.iter().zip(weights.iter()).map(|(q, w)| q.iter().zip(w).map(|(x, y)| f64::from(*x) * y).sum::<f64>()).sum();
+            if weight <= 0.0 { return; }
+            res.push((batch.clone(), cost.clone(), QueueSimulation::new(batch, times).turnaround, weight));
+        });
+        return res;
+    };
+    let no_carry = CostVec::zeros((1, MATERIAL_ORDER.len()));
+    let candidates = candidates_with(&no_carry);
+    let first_candidates = if carry.iter().any(|x| *x != 0) { Some(candidates_with(carry)) } else { None };
+
+    let mut plan: Vec<PlannedBatch> = Vec::new();
+    let mut t: Seconds = 0;
+    loop {
+        let spent = spent(&plan);
+        let (step_candidates, step_carry) = match first_candidates.as_ref() {
+            Some(first_candidates) if plan.is_empty() => (first_candidates, carry),
+            _ => (&candidates, &no_carry),
+        };
+
+        // (candidate, start, ready, weight per second)
+        let mut best: Option<(usize, Seconds, Seconds, f64)> = None;
+        for (i, (_, cost, turnaround, weight)) in step_candidates.iter().enumerate() {
+            let Some(start) = income.earliest_affordable(t, &spent, &(cost - step_carry)) else { continue; };
+            let ready = start + turnaround;
+            if ready > horizon { continue; }
+
+            let rate = weight / f64::from((ready - t).max(1));
+            if best.is_none_or(|(_, _, best_ready, best_rate)| rate > best_rate || (rate == best_rate && ready < best_ready)) {
+                best = Some((i, start, ready, rate));
+            }
+        }
+
+        let Some((i, start, ready, _)) = best else { break; };
+        let (batch, cost, _, weight) = &step_candidates[i];
+        plan.push(PlannedBatch { batch: batch.clone(), cost: cost.clone(), carry: step_carry.clone(), start, ready, weight: *weight });
+        t = ready;
+    }
+    return plan;
+}
+
+// Materials taken from the stock by every batch of a plan
+pub fn spent(plan: &[PlannedBatch]) -> Vec<u64> {
+    let mut res: Vec<u64> = vec![0; MATERIAL_ORDER.len()];
+    for p in plan {
+        for (s, x) in res.iter_mut().zip(p.paid().iter()) {
+            *s += u64::from(*x);
+        }
+    }
+    return res;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cost_metric::CostMetric, helper::batch_cost, set_test_args};
+
+    #[test]
+    fn only_the_first_batch_goes_with_the_carry() {
+        set_test_args();
+        let weights: Vec<Vec<f64>> = ITEM_SET_CATEGORY_ORDER.iter().map(|c| vec![1.0; usize::from(c.size())]).collect();
+        let times: Vec<Vec<Seconds>> = ITEM_SET_CATEGORY_ORDER.iter().map(|c| vec![60; usize::from(c.size())]).collect();
+        let carry = CostVec::from_shape_vec((1, 4), vec![60, 0, 0, 0]).unwrap();
+        let income = Income::new(vec![400, 0, 0, 0], vec![0, 0, 0, 0]);
+
+        let plan = plan_shift(1, &CostMetric::Stackable, &income, 3600, &weights, &times, &carry);
+        assert!(plan.len() >= 2, "expected at least two batches, planned {}", plan.len());
+        for (i, p) in plan.iter().enumerate() {
+            assert_eq!(p.carry, if i == 0 { carry.clone() } else { CostVec::zeros((1, 4)) }, "batch {i} carry");
+            assert_eq!(p.cost, batch_cost(&p.batch) + &p.carry, "batch {i} cost");
+            assert!(CostMetric::Stackable.satisfies(&p.cost), "batch {i} cost {} is not stackable", p.cost);
+        }
+        let paid: u64 = spent(&plan).iter().sum();
+        assert!(paid <= 400, "paid {paid} bmat of the 400 received");
+    }
+}
@@ -0,0 +1,109 @@
+use std::{fs::File, io::{BufWriter, Write}, path::PathBuf};
+
+use crate::{cost_metric::{CostMetric, Metric}, helper::{format_batch, format_batch_extras, format_cost_vector}, model::{crate_limits::suffix_sums, item_set::{check_item_minimums_searched, find_item, item_crate_bounds, min_crates_from, output_legend_file}}, options::read_json_weights, Batch, CostVec, QueueVec, ARGS, CARRY, CRATE_LIMITS, ITEM_SET_CATEGORY_ORDER, ITEM_SET_NAME, JSON_OPTIONS, OUTPUT_PATH, TRUCK_SIZE_U16};
+
+// Reads item weights into per category, per item weights for the current ItemSet
+// Grouped items take the largest weight of any item in their group
+pub fn item_weights(weights_path: &PathBuf) -> Vec<Vec<f64>> {
+    let mut weights: Vec<Vec<f64>> = ITEM_SET_CATEGORY_ORDER.iter().map(|c| vec![0.0; usize::from(c.size())]).collect();
+    for (name, weight) in read_json_weights(weights_path) {
+        if weight < 0.0 { panic!("Weights: [{name}] weight must be >= 0."); }
+        let (category, item) = find_item(&name).unwrap_or_else(|| panic!("Weights: [{name}] is not an item in {}.", *ITEM_SET_NAME));
+        weights[category][item] = weights[category][item].max(weight);
+    }
+    return weights;
+}
+
+// Finds the `top` batches of the first n categories with the largest total weighted crates that satisfy the metric
+// n defaults to the last category with a weighted item
+pub fn find_n_weighted_batches_with_metric(n: Option<usize>, metric: &dyn Metric, weights_path: &PathBuf, top: usize) {
+    let weights = item_weights(weights_path);
+    let n = n.unwrap_or(weights.iter().rposition(|w| w.iter().any(|x| *x > 0.0)).map_or(1, |c| c + 1));
+
+    if n < 1 { panic!("n must be >= 1, was provided {n}"); }
+    if top < 1 { panic!("top must be >= 1, was provided {top}"); }
+    // Crates the item minimums still need after every category
+    let bounds = item_crate_bounds(JSON_OPTIONS.item_crates.as_ref());
+    check_item_minimums_searched(&bounds, n);
+    CRATE_LIMITS.check_searched(n);
+    let min_crates = min_crates_from(&bounds);
+    let queue_weight = |category: usize, queue: &QueueVec| -> f64 { queue.iter().zip(&weights[category]).map(|(q, w)| f64::from(*q) * w).sum() };
+
+    // Base valid queues for the first n categories as (queue, cost, item_count, weight), highest weight popped first
+    let base_queues: Vec<Vec<(QueueVec, CostVec, u16, f64)>> = ITEM_SET_CATEGORY_ORDER.iter().take(n).enumerate().map(|(i, c)| {
+                                                                                                     let mut res: Vec<(QueueVec, CostVec, u16, f64)> = c.generate_valid_queue_vec().into_iter()
+                                                                                                                                                          .map(|(q, cost, count)| { let w = queue_weight(i, &q); (q, cost, count, w) })
+                                                                                                                                                          .collect();
+                                                                                                     res.sort_by(|a, b| a.3.total_cmp(&b.3));
+                                                                                                     return res;
+                                                                                                   }).collect();
+
+    // Largest weight each category (and all categories after it) can still add, used to prune the search
+    let mut remaining_max: Vec<f64> = base_queues.iter().map(|qs| qs.last().map_or(0.0, |q| q.3)).collect();
+    remaining_max.push(0.0);
+    for i in (0..base_queues.len()).rev() {
+        remaining_max[i] += remaining_max[i + 1];
+    }
+    // Most crates every category onwards can still add, for --min-crates
+    let max_crates = suffix_sums(&base_queues.iter().map(|qs| qs.iter().map(|q| q.2).max().unwrap_or(0)).collect::<Vec<u16>>());
+
+    // Best batches so far, sorted by descending weight
+    let mut best: Vec<(Batch, CostVec, f64)> = Vec::new();
+
+    // Stack for DFS: Vec<(batch, cost, item_count, weight)>
+    let mut stack: Vec<(Batch, CostVec, u16, f64)> = Vec::new();
+    for (queue, cost, item_count, weight) in base_queues.first().unwrap().clone() {
+        let new_cost = cost + &*CARRY;
+        if CostMetric::Affordable.satisfies_metric(&new_cost) && item_count + min_crates[1] <= TRUCK_SIZE_U16 && item_count + max_crates[1] >= CRATE_LIMITS.min_crates {
+            let new_batch = vec![queue];
+            if metric.can_still_satisfy(&new_batch, &new_cost, item_count, n - 1) {
+                stack.push((new_batch, new_cost, item_count, weight));
+            }
+        }
+    }
+
+    while let Some((cur_batch, cur_cost, cur_item_count, cur_weight)) = stack.pop() {
+        // A batch can not make the top if it can not reach the weight of the worst top batch
+        if best.len() == top && cur_weight + remaining_max[cur_batch.len()] <= best.last().unwrap().2 { continue; }
+
+        // Exit conditions
+        if cur_batch.len() == n {
+            if metric.satisfies(&cur_cost) {
+                let idx = best.partition_point(|(_, _, w)| *w >= cur_weight);
+                best.insert(idx, (cur_batch, cur_cost, cur_weight));
+                best.truncate(top);
+            }
+            continue;
+        }
+
+        // For all base queues in the next category
+        for (next_queue, next_cost, next_item_count, next_weight) in base_queues[cur_batch.len()].iter() {
+            let new_cost = cur_cost.clone() + next_cost;
+            let new_item_count = cur_item_count + next_item_count;
+
+            // If the new batch is affordable, the number of items, with the items the minimums still need, < TRUCK_SIZE_U16
+            // and the remaining categories can still reach the fewest crates of a batch, push to stack
+            if CostMetric::Affordable.satisfies_metric(&new_cost) && new_item_count + min_crates[cur_batch.len() + 1] <= TRUCK_SIZE_U16
+               && new_item_count + max_crates[cur_batch.len() + 1] >= CRATE_LIMITS.min_crates {
+                let mut new_batch = cur_batch.clone();
+                new_batch.push(next_queue.clone());
+                if metric.can_still_satisfy(&new_batch, &new_cost, new_item_count, n - new_batch.len()) {
+                    stack.push((new_batch, new_cost, new_item_count, cur_weight + next_weight));
+                }
+            }
+        }
+    }
+
+    let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
+    let file_str = format!("{n}_weighted_batches_with_{}_{}.txt", metric.file_str(), output_suffix);
+    let mut output = if ARGS.output { Some(BufWriter::new(File::create(OUTPUT_PATH.join(&file_str)).unwrap())) } else { None };
+    if ARGS.output && !ARGS.output_batch_long { output_legend_file(); }
+
+    for (batch, cost, weight) in best {
+        let res = format!("Batch : {}\nCost  : {}\nWeight: {}{}", format_batch(&batch), format_cost_vector(&cost), weight, format_batch_extras(&batch, &cost));
+        match output {
+            Some(ref mut f) => { let _ = writeln!(f, "{res}"); },
+            None => println!("{res}"),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::{collections::{HashMap, HashSet}, fs::File, io::{BufWriter, Write}};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{cost_metric::{CostMetric, Metric}, helper::{format_batch, format_batch_extras, format_cost_vector}, model::item_set::{check_item_minimums_searched, item_crate_bounds, output_legend_file}, Batch, CostNum, CostVec, QueueVec, ARGS, CARRY, CRATE_LIMITS, ITEM_SET_CATEGORY_ORDER, JSON_OPTIONS, OUTPUT_PATH, TRUCK_SIZE_U16};
+
+// (category, cost, item_count) -> number of batches completed from this state that satisfy the metric
+type CountMemo = HashMap<(usize, Vec<CostNum>, u16), u128>;
+
+// Samples up to `count` distinct batches of the first n categories uniformly from all batches that satisfy the metric
+// Every category queue is picked with probability proportional to the number of valid batches it leads to
+// The same seed always gives the same sample
+pub fn sample_n_batches_with_metric(n: usize, metric: &dyn Metric, count: usize, seed: u64, max_attempts: u64) {
+    let (samples, total) = sample_n_batches(n, metric, count, seed, max_attempts);
+
+    let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
+    let file_str = format!("{n}_batches_sample_{count}_seed_{seed}_with_{}_{}.txt", metric.file_str(), output_suffix);
+    let mut output = if ARGS.output { Some(BufWriter::new(File::create(OUTPUT_PATH.join(&file_str)).unwrap())) } else { None };
+    if ARGS.output && !ARGS.output_batch_long { output_legend_file(); }
+
+    for (batch, cost) in samples.iter() {
+        let res = format!("Batch: {}\nCost : {}{}", format_batch(batch), format_cost_vector(cost), format_batch_extras(batch, cost));
+        match output {
+            Some(ref mut f) => { let _ = writeln!(f, "{res}"); },
+            None => println!("{res}"),
+        }
+    }
+    println!("Sampled {} of {total} batches", samples.len());
+}
+
+// Up to `count` distinct sampled batches with their costs, and the number of batches they were sampled from
+pub fn sample_n_batches(n: usize, metric: &dyn Metric, count: usize, seed: u64, max_attempts: u64) -> (Vec<(Batch, CostVec)>, u128) {
+    if n < 1 { panic!("n must be >= 1, was provided {n}"); }
+    check_item_minimums_searched(&item_crate_bounds(JSON_OPTIONS.item_crates.as_ref()), n);
+    CRATE_LIMITS.check_searched(n);
+
+    // Base valid queues for the first n categories
+    let base_queues: Vec<Vec<(QueueVec, CostVec, u16)>> = ITEM_SET_CATEGORY_ORDER.iter().take(n).map(|c| c.generate_valid_queue_vec()).collect();
+
+    let mut memo: CountMemo = HashMap::new();
+    let start_cost = CARRY.clone();
+    let total = count_completions(&base_queues, metric, &mut Vec::new(), &start_cost, 0, &mut memo);
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut seen: HashSet<Vec<usize>> = HashSet::new();
+    let mut samples: Vec<(Batch, CostVec)> = Vec::new();
+
+    let mut attempts: u64 = 0;
+    while total > 0 && samples.len() < count && (samples.len() as u128) < total && attempts < max_attempts {
+        attempts += 1;
+
+        // Walk down the categories, picking each queue with probability (batches below it / batches below the current state)
+        let mut picks: Vec<usize> = Vec::with_capacity(base_queues.len());
+        let mut batch: Batch = Vec::with_capacity(base_queues.len());
+        let mut cost = start_cost.clone();
+        let mut item_count: u16 = 0;
+        let mut remaining = rng.gen_range(0..total);
+        for queues in base_queues.iter() {
+            for (pick, (next_queue, next_cost, next_item_count)) in queues.iter().enumerate() {
+                let new_cost = cost.clone() + next_cost;
+                let new_item_count = item_count + next_item_count;
+                if !CostMetric::Affordable.satisfies_metric(&new_cost) || new_item_count > TRUCK_SIZE_U16 { continue; }
+
+                batch.push(next_queue.clone());
+                let below = count_completions(&base_queues, metric, &mut batch, &new_cost, new_item_count, &mut memo);
+                if remaining < below {
+                    picks.push(pick);
+                    cost = new_cost;
+                    item_count = new_item_count;
+                    break;
+                }
+                batch.pop();
+                remaining -= below;
+            }
+        }
+
+        if seen.contains(&picks) { continue; }
+
+        seen.insert(picks);
+        samples.push((batch, cost));
+    }
+    return (samples, total);
+}
+
+// Counts the batches that can be completed from the partial batch onwards that satisfy the metric
+// Pruning with the metric only skips states that complete no batch, so the counts only depend on the category, cost and item count
+fn count_completions(base_queues: &[Vec<(QueueVec, CostVec, u16)>], metric: &dyn Metric, partial: &mut Batch, cost: &CostVec, item_count: u16, memo: &mut CountMemo) -> u128 {
+    let i = partial.len();
+    if i == base_queues.len() {
+        return u128::from(item_count >= CRATE_LIMITS.min_crates && metric.satisfies(cost));
+    }
+
+    let key = (i, cost.iter().copied().collect(), item_count);
+    if let Some(res) = memo.get(&key) { return *res; }
+
+    let mut res: u128 = 0;
+    for (next_queue, next_cost, next_item_count) in base_queues[i].iter() {
+        let new_cost = cost.clone() + next_cost;
+        let new_item_count = item_count + next_item_count;
+
+        // Only affordable batches with the number of items < TRUCK_SIZE_U16 that can still satisfy the metric are counted
+        if CostMetric::Affordable.satisfies_metric(&new_cost) && new_item_count <= TRUCK_SIZE_U16 {
+            partial.push(next_queue.clone());
+            if metric.can_still_satisfy(partial, &new_cost, new_item_count, base_queues.len() - partial.len()) {
+                res += count_completions(base_queues, metric, partial, &new_cost, new_item_count, memo);
+            }
+            partial.pop();
+        }
+    }
+    memo.insert(key, res);
+    return res;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{algo::n_batches::visit_n_batches_with_metric, set_test_args};
+
+    #[test]
+    fn same_seed_gives_the_same_sample() {
+        set_test_args();
+        let (first, total) = sample_n_batches(1, &CostMetric::Stackable, 20, 7, 1_000_000);
+        assert_eq!(first.len(), 20);
+        assert_eq!(sample_n_batches(1, &CostMetric::Stackable, 20, 7, 1_000_000), (first.clone(), total));
+        assert_ne!(sample_n_batches(1, &CostMetric::Stackable, 20, 8, 1_000_000).0, first);
+    }
+
+    #[test]
+    fn samples_only_and_eventually_every_valid_batch() {
+        set_test_args();
+        let metric: CostMetric = "stacks<=1".parse().unwrap();
+        let mut expected: Vec<Batch> = Vec::new();
+        visit_n_batches_with_metric(1, &metric, &Vec::new(), &CARRY, |batch, _| expected.push(batch.clone()));
+
+        let (samples, total) = sample_n_batches(1, &metric, usize::MAX, 0, 1_000_000);
+        assert_eq!(total, expected.len() as u128);
+        assert_eq!(samples.len(), expected.len());
+        assert!(samples.iter().all(|(batch, _)| expected.contains(batch)));
+    }
+}
@@ -0,0 +1,151 @@
+use std::{fs::File, io::{BufWriter, Write}, path::PathBuf};
+
+use clap::ValueEnum;
+
+use crate::{algo::decompose::decompose_order, cost_metric::{count_stacks, Metric}, helper::{batch_cost, format_batch, format_batch_extras, format_cost_vector, read_batches}, model::{item_set::output_legend_file, production::{batch_times_estimated, item_production_times, queue_durations, require_batch_production_times, Seconds}}, Batch, CostVec, ARGS, CARRY, FACILITY, ITEM_SET_CATEGORY_ORDER, MATERIAL_COUNT, OUTPUT_PATH, TRUCK_SIZE_U16};
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, ValueEnum)]
+pub enum ScheduleObjective {
+    // Finish every batch as early as possible
+    Makespan,
+    // Carry materials to the factories in as few truck trips as possible
+    Trips,
+}
+
+// A batch queued at a factory
+#[derive(Debug, Clone)]
+pub struct ScheduledBatch {
+    pub batch: Batch,
+    pub cost: CostVec,
+    // Time the first category queue of the batch starts
+    pub start: Seconds,
+    // Time the last crate of the batch is done
+    pub ready: Seconds,
+}
+
+// Every batch queued at a single factory
+// A category queue only takes the next batch's orders once it is done with the previous batch, so a queue never holds more than one batch
+#[derive(Debug, Clone)]
+pub struct FactoryTimeline {
+    pub batches: Vec<ScheduledBatch>,
+    // Time every category queue is free again
+    category_free: Vec<Seconds>,
+    // Total cost of every batch, delivered together
+    cost: CostVec,
+}
+
+impl Default for FactoryTimeline {
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+impl FactoryTimeline {
+    pub fn new() -> FactoryTimeline {
+        return FactoryTimeline { batches: Vec::new(), category_free: vec![0; ITEM_SET_CATEGORY_ORDER.len()], cost: CostVec::zeros((1, *MATERIAL_COUNT)) };
+    }
+
+    // (start, ready) of a batch if it were queued next
+    pub fn place(&self, durations: &[Seconds]) -> (Seconds, Seconds) {
+        let used: Vec<usize> = (0..durations.len()).filter(|c| durations[*c] != 0).collect();
+        let start = used.iter().map(|c| self.category_free[*c]).min().unwrap_or(self.ready());
+        let ready = used.iter().map(|c| self.category_free[*c] + durations[*c]).max().unwrap_or(self.ready());
+        return (start, ready);
+    }
+
+    pub fn push(&mut self, batch: Batch, cost: CostVec, durations: &[Seconds]) {
+        let (start, ready) = self.place(durations);
+        for (c, duration) in durations.iter().enumerate() {
+            if *duration != 0 { self.category_free[c] += duration; }
+        }
+        self.cost = &self.cost + &cost;
+        self.batches.push(ScheduledBatch { batch, cost, start, ready });
+    }
+
+    // Time the last batch is ready
+    pub fn ready(&self) -> Seconds {
+        return self.batches.iter().map(|b| b.ready).max().unwrap_or(0);
+    }
+
+    // Truck trips to carry the materials of every batch, partial stacks of different batches share a slot
+    pub fn trips(&self) -> u16 {
+        return self.trips_with(&CostVec::zeros((1, *MATERIAL_COUNT)));
+    }
+
+    fn trips_with(&self, cost: &CostVec) -> u16 {
+        return count_stacks(&(&self.cost + cost)).div_ceil(TRUCK_SIZE_U16);
+    }
+}
+
+// Assigns batches to factories, greedily placing the largest batches first where they hurt the objective least
+pub fn schedule_batches(batches: Vec<(Batch, CostVec)>, factories: usize, objective: ScheduleObjective) -> Vec<FactoryTimeline> {
+    if factories < 1 { panic!("factories must be >= 1, was provided {factories}"); }
+    for (batch, _) in batches.iter() {
+        if batch.iter().any(|q| q.sum() > FACILITY.max_queue_crates()) {
+            panic!("Schedule: [{}] queues more than {} crates in a category.", format_batch(batch), FACILITY.max_queue_crates());
+        }
+    }
+
+    if objective == ScheduleObjective::Makespan {
+        require_batch_production_times(&batches.iter().map(|(b, _)| b.clone()).collect::<Vec<Batch>>(), "schedule --objective makespan");
+    }
+
+    let times = item_production_times();
+    let mut batches: Vec<(Batch, CostVec, Vec<Seconds>)> = batches.into_iter().map(|(b, c)| { let d = queue_durations(&b, &times); (b, c, d) }).collect();
+    match objective {
+        ScheduleObjective::Makespan => batches.sort_by_key(|(_, _, d)| std::cmp::Reverse(d.iter().max().copied().unwrap_or(0))),
+        ScheduleObjective::Trips => batches.sort_by_key(|(_, c, _)| std::cmp::Reverse(count_stacks(c))),
+    }
+
+    let mut res: Vec<FactoryTimeline> = (0..factories).map(|_| FactoryTimeline::new()).collect();
+    for (batch, cost, durations) in batches {
+        let idx = (0..factories).min_by_key(|f| {
+                                    let ready = res[*f].place(&durations).1.max(res[*f].ready());
+                                    return match objective {
+                                        ScheduleObjective::Makespan => (ready, 0),
+                                        ScheduleObjective::Trips => (Seconds::from(res[*f].trips_with(&cost) - res[*f].trips()), ready),
+                                    };
+                                })
+                                .unwrap();
+        res[idx].push(batch, cost, &durations);
+    }
+    return res;
+}
+
+// Schedules the batches in a file, or the batches an order decomposes into, over several factories
+// Every batch is delivered on its own, so nothing is carried over between them
+pub fn schedule_with_objective(batches_path: Option<&PathBuf>, order_path: Option<&PathBuf>, metric: &dyn Metric, factories: usize, objective: ScheduleObjective) {
+    if CARRY.iter().any(|x| *x != 0) { panic!("Carry: schedule does not support --carry, batches are delivered to several factories."); }
+    let batches: Vec<(Batch, CostVec)> = match (batches_path, order_path) {
+        (Some(path), _) => read_batches(path).into_iter().map(|b| { let c = batch_cost(&b); (b, c) }).collect(),
+        (None, Some(path)) => {
+            let (batches, remainder) = decompose_order(path, metric, &CostVec::zeros((1, *MATERIAL_COUNT)));
+            if remainder.iter().any(|q| q.iter().any(|x| *x != 0)) { println!("Remainder: {}", format_batch(&remainder)); }
+            batches
+        },
+        (None, None) => panic!("Schedule: either a batches file or an order must be provided."),
+    };
+    let timelines = schedule_batches(batches, factories, objective);
+
+    let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
+    let file_str = format!("schedule_{:?}_{factories}_factories_{}.txt", objective, output_suffix);
+    let mut output = if ARGS.output { Some(BufWriter::new(File::create(OUTPUT_PATH.join(&file_str)).unwrap())) } else { None };
+    if ARGS.output && !ARGS.output_batch_long { output_legend_file(); }
+
+    let mut res = String::new();
+    for (i, timeline) in timelines.iter().enumerate() {
+        res += &format!("Factory {}: {} batches, {} trips, ready at {}s\n", i + 1, timeline.batches.len(), timeline.trips(), timeline.ready());
+        for scheduled in timeline.batches.iter() {
+            res += &format!("Batch: {}\nCost : {}\nStart: {}s\nReady: {}s{}\n", format_batch(&scheduled.batch), format_cost_vector(&scheduled.cost), scheduled.start, scheduled.ready, format_batch_extras(&scheduled.batch, &scheduled.cost));
+        }
+    }
+    res += &format!("Makespan: {}s\nTrips   : {}", timelines.iter().map(|t| t.ready()).max().unwrap_or(0), timelines.iter().map(|t| u32::from(t.trips())).sum::<u32>());
+    if timelines.iter().any(|t| t.batches.iter().any(|b| batch_times_estimated(&b.batch))) {
+        res += "\nTimes   : estimated from item costs, set production_times in the JSON options for real times";
+    }
+
+    match output {
+        Some(ref mut f) => { let _ = writeln!(f, "{res}"); },
+        None => println!("{res}"),
+    }
+}
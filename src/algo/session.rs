@@ -0,0 +1,160 @@
+use std::{collections::BTreeMap, fs::File, io::{BufWriter, Write}};
+
+use crate::{algo::n_batches::visit_n_batches_with_metric, cost_metric::{count_stacks, count_stacks_with_carry, leftover, CostMetric, Metric}, helper::{format_batch, format_batch_extras_with_carry, format_cost_vector}, model::item_set::output_legend_file, Batch, CostNum, CostVec, ARGS, CARRY, MATERIAL_ORDER, OUTPUT_PATH, TRUCK_SIZE_U16};
+
+// A batch of a session, with the materials rolled over from the previous batch
+pub struct SessionBatch {
+    pub batch: Batch,
+    pub cost: CostVec,
+    // Materials left in the truck before the batch
+    pub carry: CostVec,
+    // Materials left in the truck after the batch
+    pub leftover: CostVec,
+}
+
+impl SessionBatch {
+    // Materials pulled from storage for the batch, whole stacks of what the carry does not cover
+    pub fn pulled(&self) -> CostVec {
+        let mut res = &self.cost + &self.leftover;
+        for (x, c) in res.iter_mut().zip(self.carry.iter()) {
+            *x = x.saturating_sub(*c);
+        }
+        return res;
+    }
+}
+
+// Best way found to reach a leftover after some number of batches
+#[derive(Clone)]
+struct SessionStep {
+    // Leftover before the last batch
+    prev: Vec<CostNum>,
+    // Index of the last batch in the candidates
+    candidate: usize,
+    // Stacks pulled over every batch so far
+    stacks: u32,
+}
+
+// Finds k batches of the first n categories that satisfy the metric, where the partial stacks each batch leaves over roll into the next
+// Minimises the materials left over after the last batch, ties are broken by pulling the most stacks
+pub fn sequence_session_with_metric(n: usize, metric: &dyn Metric, k: usize) {
+    let session = sequence_session(n, metric, k, &CARRY);
+
+    let output_suffix = if ARGS.output_batch_long { String::from("long") } else { String::from("short")};
+    let file_str = format!("{n}_session_of_{k}_with_{}_{}.txt", metric.file_str(), output_suffix);
+    let mut output = if ARGS.output { Some(BufWriter::new(File::create(OUTPUT_PATH.join(&file_str)).unwrap())) } else { None };
+    if ARGS.output && !ARGS.output_batch_long { output_legend_file(); }
+
+    let Some(session) = session else {
+        println!("No session of {k} batches satisfying {metric} found");
+        return;
+    };
+
+    for (i, s) in session.iter().enumerate() {
+        let pulled = s.pulled();
+        let res = format!("Batch {}: {}\nCost : {}\nPull : {} ({} stacks)\nLeft : {}{}", i + 1, format_batch(&s.batch), format_cost_vector(&s.cost),
+                          format_cost_vector(&pulled), count_stacks(&pulled), format_cost_vector(&s.leftover), format_batch_extras_with_carry(&s.batch, &(&s.cost + &s.leftover), &s.carry));
+        match output {
+            Some(ref mut f) => { let _ = writeln!(f, "{res}"); },
+            None => println!("{res}"),
+        }
+    }
+    let stacks: u16 = session.iter().map(|s| count_stacks(&s.pulled())).sum();
+    let res = format!("Leftover: {} after {k} batches, {stacks} stacks pulled", format_cost_vector(&session.last().unwrap().leftover));
+    match output {
+        Some(ref mut f) => { let _ = writeln!(f, "{res}"); },
+        None => println!("{res}"),
+    }
+}
+
+// k batches of the first n categories, the first one going in the truck with the carry, that leave the least over after the last batch
+// Every batch satisfies the metric together with the materials carried into it, None if no session exists
+pub fn sequence_session(n: usize, metric: &dyn Metric, k: usize, carry: &CostVec) -> Option<Vec<SessionBatch>> {
+    if k < 1 { panic!("k must be >= 1, was provided {k}"); }
+
+    // The carry changes from batch to batch, collect every batch that fits a truck on its own and check the metric once the carry is known
+    // Batches of the same cost are interchangeable, keep one of each cost
+    let mut candidates: BTreeMap<Vec<CostNum>, (Batch, CostVec)> = BTreeMap::new();
+    visit_n_batches_with_metric(n, &CostMetric::Affordable, &Vec::new(), &CostVec::zeros((1, MATERIAL_ORDER.len())), |batch, cost| {
+        candidates.entry(cost.iter().copied().collect()).or_insert_with(|| (batch.clone(), cost.clone()));
+    });
+    let candidates: Vec<(Batch, CostVec)> = candidates.into_values().collect();
+
+    // layers[i] maps every leftover reachable after i + 1 batches to the best way of reaching it
+    let mut layers: Vec<BTreeMap<Vec<CostNum>, SessionStep>> = Vec::new();
+    let start: BTreeMap<Vec<CostNum>, SessionStep> = BTreeMap::from([(carry.iter().copied().collect(), SessionStep { prev: Vec::new(), candidate: 0, stacks: 0 })]);
+    for i in 0..k {
+        let prev_layer = if i == 0 { &start } else { &layers[i - 1] };
+        let mut layer: BTreeMap<Vec<CostNum>, SessionStep> = BTreeMap::new();
+        for (step_carry, step) in prev_layer {
+            let step_carry = CostVec::from_shape_vec((1, step_carry.len()), step_carry.clone()).unwrap();
+            for (idx, (_, cost)) in candidates.iter().enumerate() {
+                if !metric.satisfies(&(cost + &step_carry)) { continue; }
+                let left = leftover(cost, &step_carry);
+                // The truck holds the carried over materials and the pulled stacks
+                if count_stacks(&(cost + &left)) > TRUCK_SIZE_U16 { continue; }
+
+                let stacks = step.stacks + u32::from(count_stacks_with_carry(cost, &step_carry));
+                let key: Vec<CostNum> = left.iter().copied().collect();
+                if layer.get(&key).is_some_and(|s| s.stacks >= stacks) { continue; }
+                layer.insert(key, SessionStep { prev: step_carry.iter().copied().collect(), candidate: idx, stacks });
+            }
+        }
+        layers.push(layer);
+    }
+
+    let (last, _) = layers.last().unwrap().iter()
+                          .min_by_key(|(left, step)| (left.iter().map(|x| u32::from(*x)).sum::<u32>(), std::cmp::Reverse(step.stacks)))?;
+
+    // Walk back from the smallest final leftover
+    let mut session: Vec<SessionBatch> = Vec::new();
+    let mut key = last.clone();
+    for layer in layers.iter().rev() {
+        let step = &layer[&key];
+        let (batch, cost) = &candidates[step.candidate];
+        session.push(SessionBatch { batch: batch.clone(),
+                                    cost: cost.clone(),
+                                    carry: CostVec::from_shape_vec((1, step.prev.len()), step.prev.clone()).unwrap(),
+                                    leftover: CostVec::from_shape_vec((1, key.len()), key.clone()).unwrap() });
+        key = step.prev.clone();
+    }
+    session.reverse();
+    return Some(session);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cost_metric::CostMetric, set_test_args};
+
+    #[test]
+    fn every_batch_rolls_its_leftover_into_the_next() {
+        set_test_args();
+        let carry = CostVec::from_shape_vec((1, 4), vec![60, 0, 0, 0]).unwrap();
+        let session = sequence_session(1, &CostMetric::Stackable, 3, &carry).unwrap();
+        assert_eq!(session.len(), 3);
+
+        let mut expected_carry = carry.clone();
+        for (i, s) in session.iter().enumerate() {
+            assert_eq!(s.carry, expected_carry, "batch {i} carry");
+            assert!(CostMetric::Stackable.satisfies(&(&s.cost + &s.carry)), "batch {i} with its carry is not stackable");
+            assert_eq!(s.leftover, leftover(&s.cost, &s.carry), "batch {i} leftover");
+            assert_eq!(&s.carry + &s.pulled(), &s.cost + &s.leftover, "batch {i} pull");
+            expected_carry = s.leftover.clone();
+        }
+    }
+
+    #[test]
+    fn a_single_batch_leaves_the_least_possible() {
+        set_test_args();
+        let carry = CostVec::from_shape_vec((1, 4), vec![30, 0, 0, 0]).unwrap();
+        let session = sequence_session(1, &CostMetric::Affordable, 1, &carry).unwrap();
+
+        let mut least = u16::MAX;
+        visit_n_batches_with_metric(1, &CostMetric::Affordable, &Vec::new(), &CostVec::zeros((1, 4)), |_, cost| {
+            if CostMetric::Affordable.satisfies(&(cost + &carry)) && count_stacks(&(cost + &leftover(cost, &carry))) <= TRUCK_SIZE_U16 {
+                least = least.min(leftover(cost, &carry).sum());
+            }
+        });
+        assert_eq!(session[0].leftover.sum(), least);
+    }
+}
@@ -1,6 +1,6 @@
-use std::fmt;
+use std::{fmt, str::FromStr};
 
-use crate::{CostVec, MATERIAL_ORDER, TRUCK_SIZE_U16};
+use crate::{model::material::Material, Batch, CostVec, ITEM_SET_NAME, MATERIAL_ORDER, TRUCK_SIZE_U16};
 use strum_macros::EnumIter;
 
 
@@ -8,6 +8,151 @@ pub fn count_stacks(cv: &CostVec) -> u16 {
     return cv.iter().enumerate().map(|(idx, x)| x.div_ceil(MATERIAL_ORDER[idx].stack_value())).sum::<u16>();
 }
 
+// Stacks that have to be pulled for a cost when the carried over materials are used first
+pub fn count_stacks_with_carry(cv: &CostVec, carry: &CostVec) -> u16 {
+    return cv.iter().zip(carry.iter()).enumerate()
+             .map(|(idx, (x, c))| x.saturating_sub(*c).div_ceil(MATERIAL_ORDER[idx].stack_value()))
+             .sum::<u16>();
+}
+
+// Materials left over from the carried over materials and the pulled stacks once a cost is paid
+// Only the partial stack stays in the truck, whole stacks the cost does not need go back to storage
+pub fn leftover(cv: &CostVec, carry: &CostVec) -> CostVec {
+    let mut res = carry.clone();
+    for (idx, (l, x)) in res.iter_mut().zip(cv.iter()).enumerate() {
+        let stack_value = MATERIAL_ORDER[idx].stack_value();
+        *l = if *l >= *x { (*l - x) % stack_value } else { (stack_value - (x - *l) % stack_value) % stack_value };
+    }
+    return res;
+}
+
+// A rule every batch of a search has to satisfy, implement it to plug custom rules into the searches in algo
+pub trait Metric: fmt::Display + Sync {
+    // Whether a complete batch with this cost is accepted
+    fn satisfies(&self, cv: &CostVec) -> bool;
+
+    // Whether a partial batch could still be completed into a batch that satisfies the metric, used to prune searches
+    // crate_count is the number of crates in the partial batch, remaining_categories the number of categories still to be searched
+    // Must only be false when no completion satisfies the metric, costs and crate counts only ever grow during a search
+    fn can_still_satisfy(&self, _partial: &Batch, _cost: &CostVec, _crate_count: u16, _remaining_categories: usize) -> bool {
+        return true;
+    }
+
+    // Ranked metrics output the batches with the smallest rank first
+    fn is_ranked(&self) -> bool {
+        return false;
+    }
+
+    fn rank(&self, _cv: &CostVec) -> Option<f64> {
+        return None;
+    }
+
+    // Display without the characters file systems do not allow in file names
+    fn file_str(&self) -> String {
+        return self.to_string().chars().filter(|c| !c.is_whitespace()).map(|c| match c {
+            '&' => String::from("_and_"),
+            '|' => String::from("_or_"),
+            '<' => String::from("lt"),
+            '>' => String::from("gt"),
+            '=' => String::from("eq"),
+            '!' => String::from("not"),
+            ':' => String::from("-"),
+            '/' | '\\' | '*' | '?' | '"' => String::from("_"),
+            c => c.to_string(),
+        }).collect();
+    }
+}
+
+impl Metric for CostMetric {
+    fn satisfies(&self, cv: &CostVec) -> bool {
+        return self.satisfies_metric(cv);
+    }
+
+    fn can_still_satisfy(&self, _partial: &Batch, cost: &CostVec, _crate_count: u16, _remaining_categories: usize) -> bool {
+        return self.cost_can_still_satisfy(cost);
+    }
+
+    fn is_ranked(&self) -> bool {
+        return match self {
+            Self::Ratio(..) => true,
+            Self::And(metrics) | Self::Or(metrics) => metrics.iter().any(|m| m.is_ranked()),
+            _ => false,
+        };
+    }
+
+    fn rank(&self, cv: &CostVec) -> Option<f64> {
+        return match self {
+            Self::Ratio(..) => self.ratio_distance(cv),
+            // Every part is satisfied, so a batch is as far off as its furthest ratio
+            Self::And(metrics) => metrics.iter().filter_map(|m| m.rank(cv)).reduce(f64::max),
+            // Only the parts a batch satisfies count, a batch only satisfying unranked parts is ranked last
+            Self::Or(metrics) => metrics.iter().filter(|m| m.satisfies_metric(cv)).filter_map(|m| m.rank(cv)).reduce(f64::min),
+            _ => None,
+        };
+    }
+}
+
+// Value of a cost that a metric expression can compare
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone)]
+pub enum MetricQuantity {
+    #[default]
+    Stacks,
+    Material(Material),
+}
+
+impl MetricQuantity {
+    pub fn value(&self, cv: &CostVec) -> u16 {
+        return match self {
+            Self::Stacks => count_stacks(cv),
+            Self::Material(m) => {
+                let idx = MATERIAL_ORDER.iter().position(|x| x == m)
+                                        .unwrap_or_else(|| panic!("Metric: {} is not a material of {}.", m.short_name(), *ITEM_SET_NAME));
+                cv[[0, idx]]
+            },
+        };
+    }
+}
+
+impl fmt::Display for MetricQuantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Stacks => write!(f, "stacks"),
+            Self::Material(m) => write!(f, "{}", m.short_name()),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Comparison {
+    #[default]
+    Eq,
+    Ne,
+    Le,
+    Lt,
+    Ge,
+    Gt,
+}
+
+impl Comparison {
+    // Longer operators first so "<=" is not read as "<"
+    const OPERATORS: [(&'static str, Comparison); 6] = [("==", Self::Eq), ("!=", Self::Ne), ("<=", Self::Le), (">=", Self::Ge), ("<", Self::Lt), (">", Self::Gt)];
+
+    pub fn compare(&self, lhs: u16, rhs: u16) -> bool {
+        return match self {
+            Self::Eq => lhs == rhs,
+            Self::Ne => lhs != rhs,
+            Self::Le => lhs <= rhs,
+            Self::Lt => lhs < rhs,
+            Self::Ge => lhs >= rhs,
+            Self::Gt => lhs > rhs,
+        };
+    }
+
+    pub fn operator(&self) -> &'static str {
+        return Self::OPERATORS.iter().find(|(_, c)| c == self).unwrap().0;
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, EnumIter)]
 pub enum CostMetric {
     Affordable,
@@ -16,6 +161,12 @@ pub enum CostMetric {
     Crateable,
     PerfectlyStackable(u16),
     PerfectlyCrateable(u16),
+    // Cost within a tolerance in percentage points of a material ratio, optionally also satisfying another metric
+    Ratio(Vec<u16>, u16, Option<Box<CostMetric>>),
+    // Metric expressions, e.g. "stackable & stacks==15 & emat<=200 | crateable"
+    Compare(MetricQuantity, Comparison, u16),
+    And(Vec<CostMetric>),
+    Or(Vec<CostMetric>),
 }
 
 impl CostMetric {
@@ -66,13 +217,293 @@ impl CostMetric {
                     Ok(sum) => sum == *n,
                     Err(_) => false, 
                 };
-            }
+            },
+            Self::Ratio(_, tolerance, base) => {
+                base.as_ref().is_none_or(|b| b.satisfies_metric(cv)) &&
+                self.ratio_distance(cv).is_some_and(|d| d <= f64::from(*tolerance))
+            },
+            Self::Compare(quantity, comparison, value) => comparison.compare(quantity.value(cv), *value),
+            Self::And(metrics) => metrics.iter().all(|m| m.satisfies_metric(cv)),
+            Self::Or(metrics) => metrics.iter().any(|m| m.satisfies_metric(cv)),
         }
     }
+
+    // Whether adding more to a cost could still satisfy the metric, costs only ever grow during a search
+    // Used to prune searches, so it may only be false when no larger cost satisfies the metric
+    pub fn cost_can_still_satisfy(&self, cv: &CostVec) -> bool {
+        return match self {
+            Self::Affordable => count_stacks(cv) <= TRUCK_SIZE_U16,
+            Self::NValid(n) | Self::PerfectlyStackable(n) | Self::PerfectlyCrateable(n) => count_stacks(cv) <= *n,
+            Self::Stackable | Self::Crateable => true,
+            Self::Ratio(_, _, base) => base.as_ref().is_none_or(|b| b.cost_can_still_satisfy(cv)),
+            Self::Compare(quantity, comparison, value) => match comparison {
+                Comparison::Eq | Comparison::Le => quantity.value(cv) <= *value,
+                Comparison::Lt => quantity.value(cv) < *value,
+                Comparison::Ne | Comparison::Ge | Comparison::Gt => true,
+            },
+            Self::And(metrics) => metrics.iter().all(|m| m.cost_can_still_satisfy(cv)),
+            Self::Or(metrics) => metrics.iter().any(|m| m.cost_can_still_satisfy(cv)),
+        };
+    }
+
+    // Largest difference in percentage points between the share of a material in the cost and in the target ratio
+    // None if the metric has no ratio or the cost is empty
+    pub fn ratio_distance(&self, cv: &CostVec) -> Option<f64> {
+        let Self::Ratio(ratio, _, _) = self else { return None; };
+        if let Err(e) = self.check_materials(&MATERIAL_ORDER) { panic!("Metric [{self}]: {e} for {}.", *ITEM_SET_NAME); }
+
+        let cost_total: f64 = cv.iter().map(|x| f64::from(*x)).sum();
+        let ratio_total: f64 = ratio.iter().map(|x| f64::from(*x)).sum();
+        if cost_total == 0.0 { return None; }
+
+        return Some(cv.iter().zip(ratio.iter())
+                      .map(|(x, r)| (100.0 * f64::from(*x) / cost_total - 100.0 * f64::from(*r) / ratio_total).abs())
+                      .fold(0.0, f64::max));
+    }
+}
+
+impl CostMetric {
+    // Checks every ratio in the metric has one part per material and every comparison names one of the materials
+    // The materials depend on the item set, so this can only be checked once every argument is parsed
+    pub fn check_materials(&self, materials: &[Material]) -> Result<(), String> {
+        return match self {
+            Self::Ratio(ratio, _, base) => {
+                if ratio.len() != materials.len() {
+                    let names: Vec<&str> = materials.iter().map(|m| m.short_name()).collect();
+                    return Err(format!("ratio must have {} parts ({}), was provided {}", materials.len(), names.join(":"), ratio.len()));
+                }
+                base.as_ref().map_or(Ok(()), |b| b.check_materials(materials))
+            },
+            Self::Compare(MetricQuantity::Material(m), _, _) if !materials.contains(m) => {
+                let names: Vec<&str> = materials.iter().map(|m| m.short_name()).collect();
+                Err(format!("{} is not one of the materials ({})", m.short_name(), names.join(", ")))
+            },
+            Self::And(metrics) | Self::Or(metrics) => metrics.iter().try_for_each(|m| m.check_materials(materials)),
+            _ => Ok(()),
+        };
+    }
 }
 
 impl fmt::Display for CostMetric {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            Self::Ratio(ratio, tolerance, base) => {
+                let parts: Vec<String> = ratio.iter().map(|x| x.to_string()).collect();
+                match base {
+                    Some(b) => write!(f, "Ratio({},{},{})", parts.join(":"), tolerance, b),
+                    None => write!(f, "Ratio({},{})", parts.join(":"), tolerance),
+                }
+            },
+            Self::Compare(quantity, comparison, value) => write!(f, "{}{}{}", quantity, comparison.operator(), value),
+            Self::And(metrics) => {
+                let parts: Vec<String> = metrics.iter().map(|m| if matches!(m, Self::Or(_)) { format!("({m})") } else { m.to_string() }).collect();
+                write!(f, "{}", parts.join(" & "))
+            },
+            Self::Or(metrics) => {
+                let parts: Vec<String> = metrics.iter().map(|m| m.to_string()).collect();
+                write!(f, "{}", parts.join(" | "))
+            },
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// Splits an expression on a separator outside of brackets
+fn split_top_level(s: &str, separator: char) -> Vec<&str> {
+    let mut res: Vec<&str> = Vec::new();
+    let mut depth: i32 = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                res.push(&s[start..i]);
+                start = i + c.len_utf8();
+            },
+            _ => {},
+        }
+    }
+    res.push(&s[start..]);
+    return res;
+}
+
+// Parses a single term of an expression: a bracketed expression, a comparison or a named metric
+fn parse_term(s: &str) -> Result<CostMetric, String> {
+    let s = s.trim();
+    if s.is_empty() { return Err(String::from("Metric expression has an empty term.")); }
+
+    if let Some(inner) = s.strip_prefix('(').and_then(|x| x.strip_suffix(')')) {
+        return inner.parse::<CostMetric>();
+    }
+
+    if !s.contains('(') && let Some((op, comparison)) = Comparison::OPERATORS.iter().find(|(op, _)| s.contains(op)) {
+        let (lhs, rhs) = s.split_once(op).unwrap();
+        let lhs = lhs.trim().to_lowercase();
+        let quantity = if lhs == "stacks" {
+            MetricQuantity::Stacks
+        } else {
+            MetricQuantity::Material(Material::from_short_name(&lhs).ok_or(format!("Metric [{s}]: [{lhs}] is not stacks or a material."))?)
+        };
+        let value = rhs.trim().parse::<u16>().map_err(|_| format!("Metric [{s}]: [{}] must be a number.", rhs.trim()))?;
+        return Ok(CostMetric::Compare(quantity, *comparison, value));
+    }
+
+    return parse_named_metric(s);
+}
+
+// Parses the same format Display produces, e.g. "Stackable", "PerfectlyStackable(15)" or "Ratio(3:1:0:1,5,Stackable)"
+// Metrics combine into expressions with "&" (binding tighter) and "|", e.g. "stackable & stacks==15 & emat<=200 | crateable"
+impl FromStr for CostMetric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut any: Vec<CostMetric> = Vec::new();
+        for alternative in split_top_level(s, '|') {
+            let mut all: Vec<CostMetric> = split_top_level(alternative, '&').into_iter().map(parse_term).collect::<Result<_, _>>()?;
+            any.push(if all.len() == 1 { all.pop().unwrap() } else { CostMetric::And(all) });
+        }
+        return Ok(if any.len() == 1 { any.pop().unwrap() } else { CostMetric::Or(any) });
+    }
+}
+
+// Parses a metric by name, e.g. "Stackable", "PerfectlyStackable(15)" or "Ratio(3:1:0:1,5,Stackable)"
+fn parse_named_metric(s: &str) -> Result<CostMetric, String> {
+    let s = s.trim();
+    if let Some((name, rest)) = s.split_once('(') && name.trim().eq_ignore_ascii_case("ratio") {
+        let args = rest.strip_suffix(')').ok_or(format!("Metric [{s}] is missing a closing bracket."))?;
+        let mut args = args.splitn(3, ',');
+        let ratio = args.next().unwrap().split(':')
+                        .map(|x| x.trim().parse::<u16>())
+                        .collect::<Result<Vec<u16>, _>>()
+                        .map_err(|_| format!("Metric [{s}] ratio must be numbers separated by \":\"."))?;
+        if ratio.iter().all(|x| *x == 0) { return Err(format!("Metric [{s}] ratio must not be all 0.")); }
+        let tolerance = args.next().ok_or(format!("Metric [{s}] is missing a tolerance."))?
+                            .trim().parse::<u16>().map_err(|_| format!("Metric [{s}] tolerance must be a number."))?;
+        let base = args.next().map(|b| b.parse::<CostMetric>()).transpose()?.map(Box::new);
+        return Ok(CostMetric::Ratio(ratio, tolerance, base));
+    }
+
+    let (name, arg) = match s.split_once('(') {
+        Some((name, rest)) => {
+            let arg = rest.strip_suffix(')').ok_or(format!("Metric [{s}] is missing a closing bracket."))?;
+            let arg = arg.trim().parse::<u16>().map_err(|_| format!("Metric [{s}] argument must be a number."))?;
+            (name.trim(), Some(arg))
+        },
+        None => (s, None),
+    };
+
+    return match (name.to_lowercase().as_str(), arg) {
+        ("affordable", None) => Ok(CostMetric::Affordable),
+        ("stackable", None) => Ok(CostMetric::Stackable),
+        ("crateable", None) => Ok(CostMetric::Crateable),
+        ("nvalid", Some(n)) => Ok(CostMetric::NValid(n)),
+        ("perfectlystackable", Some(n)) => Ok(CostMetric::PerfectlyStackable(n)),
+        ("perfectlycrateable", Some(n)) => Ok(CostMetric::PerfectlyCrateable(n)),
+        _ => Err(format!("Metric [{s}] is not a valid metric.")),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{set_test_args, CostNum};
+
+    fn parse(s: &str) -> CostMetric {
+        return s.parse::<CostMetric>().unwrap_or_else(|e| panic!("[{s}] failed to parse: {e}"));
+    }
+
+    // Costs are evaluated against the materials of the item set, every test uses the warden materials
+    fn cost(amounts: [CostNum; 4]) -> CostVec {
+        set_test_args();
+        return CostVec::from_shape_vec((1, 4), amounts.to_vec()).unwrap();
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let stacks = CostMetric::Compare(MetricQuantity::Stacks, Comparison::Eq, 15);
+        assert_eq!(parse("stackable & stacks==15 | crateable"), CostMetric::Or(vec![CostMetric::And(vec![CostMetric::Stackable, stacks.clone()]), CostMetric::Crateable]));
+        assert_eq!(parse("crateable | stackable & stacks==15"), CostMetric::Or(vec![CostMetric::Crateable, CostMetric::And(vec![CostMetric::Stackable, stacks.clone()])]));
+        assert_eq!(parse("stackable & (stacks==15 | crateable)"), CostMetric::And(vec![CostMetric::Stackable, CostMetric::Or(vec![stacks, CostMetric::Crateable])]));
+    }
+
+    #[test]
+    fn parses_every_comparison_operator() {
+        let emat = MetricQuantity::Material(Material::ExplosiveMaterial);
+        for (s, comparison) in [("emat==200", Comparison::Eq), ("emat!=200", Comparison::Ne), ("emat<=200", Comparison::Le),
+                                ("emat>=200", Comparison::Ge), ("emat<200", Comparison::Lt), ("emat>200", Comparison::Gt), (" EMAT <= 200 ", Comparison::Le)] {
+            assert_eq!(parse(s), CostMetric::Compare(emat.clone(), comparison, 200), "[{s}]");
+        }
+    }
+
+    #[test]
+    fn parses_named_metrics() {
+        assert_eq!(parse("Affordable"), CostMetric::Affordable);
+        assert_eq!(parse("perfectlystackable(15)"), CostMetric::PerfectlyStackable(15));
+        assert_eq!(parse("Ratio(10:3:0:1,2,Stackable)"), CostMetric::Ratio(vec![10, 3, 0, 1], 2, Some(Box::new(CostMetric::Stackable))));
+    }
+
+    #[test]
+    fn display_parses_back() {
+        for s in ["PerfectlyStackable(15)", "Ratio(10:3:0:1,2,Stackable)", "Stackable & stacks==15 | Crateable", "Stackable & (stacks<=15 | emat>0)"] {
+            let metric = parse(s);
+            assert_eq!(parse(&metric.to_string()), metric, "[{s}]");
+        }
+    }
+
+    #[test]
+    fn rejects_empty_terms() {
+        for s in ["", " ", "stackable &", "| crateable", "stackable & & crateable", "()"] {
+            assert!(s.parse::<CostMetric>().is_err(), "[{s}] should not parse");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        for s in ["Bogus", "Stackable(15)", "PerfectlyStackable", "PerfectlyStackable(15", "PerfectlyStackable(x)", "emat<=x", "foo<=3",
+                  "stacks=15", "Ratio(0:0:0:0,5)", "Ratio(1:a:0:0,5)", "Ratio(1:1:0:0)", "Ratio(1:1:0:0,5,Bogus)"] {
+            assert!(s.parse::<CostMetric>().is_err(), "[{s}] should not parse");
+        }
+    }
+
+    #[test]
+    fn rejects_metrics_not_matching_the_materials() {
+        let materials = [Material::BasicMaterial, Material::ExplosiveMaterial];
+        assert!(parse("Ratio(1:1,5)").check_materials(&materials).is_ok());
+        assert!(parse("Ratio(1:1:0,5)").check_materials(&materials).is_err());
+        assert!(parse("crateable | stackable & Ratio(1:1:0,5)").check_materials(&materials).is_err());
+        assert!(parse("emat<=200 & bmat>=100").check_materials(&materials).is_ok());
+        assert!(parse("rmat<=20").check_materials(&materials).is_err());
+        assert!(parse("crateable | stackable & (stacks<=15 | hemat==0)").check_materials(&materials).is_err());
+        assert!(parse("Ratio(1:1,5,rmat>0)").check_materials(&materials).is_err());
+    }
+
+    #[test]
+    fn ranks_ratios_inside_expressions() {
+        let cv = cost([100, 40, 0, 0]);
+        let distance = 100.0 * 100.0 / 140.0 - 50.0;
+
+        assert!(!parse("crateable | stacks<=15").is_ranked());
+        assert!(parse("crateable | Ratio(1:1:0:0,30)").is_ranked());
+        assert!(parse("crateable & Ratio(1:1:0:0,30)").is_ranked());
+
+        assert!((parse("crateable & Ratio(1:1:0:0,30)").rank(&cv).unwrap() - distance).abs() < 1e-9);
+        assert!((parse("crateable | Ratio(1:1:0:0,30)").rank(&cv).unwrap() - distance).abs() < 1e-9);
+        assert!((parse("Ratio(1:1:0:0,30) | Ratio(5:2:0:0,30)").rank(&cv).unwrap()).abs() < 1e-9);
+        // The ratio is not satisfied, so only the unranked part counts
+        assert_eq!(parse("crateable | Ratio(1:1:0:0,10)").rank(&cv), None);
+    }
+
+    #[test]
+    fn leftover_keeps_only_the_partial_stack() {
+        // The carry covers the cost, whole stacks it does not need go back
+        assert_eq!(leftover(&cost([40, 0, 0, 0]), &cost([60, 0, 0, 0])), cost([20, 0, 0, 0]));
+        assert_eq!(leftover(&cost([40, 0, 0, 0]), &cost([260, 0, 0, 0])), cost([20, 0, 0, 0]));
+        assert_eq!(leftover(&cost([60, 0, 0, 0]), &cost([60, 0, 0, 0])), cost([0, 0, 0, 0]));
+        // Stacks are pulled for what the carry does not cover
+        assert_eq!(leftover(&cost([150, 0, 0, 0]), &cost([60, 0, 0, 0])), cost([10, 0, 0, 0]));
+        assert_eq!(leftover(&cost([160, 0, 0, 0]), &cost([60, 0, 0, 0])), cost([0, 0, 0, 0]));
+        assert_eq!(leftover(&cost([0, 25, 0, 130]), &cost([0, 0, 0, 0])), cost([0, 75, 0, 70]));
+        assert_eq!(count_stacks_with_carry(&cost([150, 25, 0, 130]), &cost([60, 0, 0, 0])), 4);
     }
 }
\ No newline at end of file
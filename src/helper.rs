@@ -1,15 +1,72 @@
-use crate::{model::item_set::ItemSetCategory, Batch, CostVec, CATEGORY_COUNT, ITEM_SET_CATEGORY_ORDER};
-use std::fmt::Write;
+use crate::{model::{item_set::{expand::{count_expansions, expand_batch}, find_item, ItemSetCategory}, loading_plan::LoadingPlan, production::{item_production_times, QueueSimulation}, refinery::Refinery}, Batch, CostVec, OrderNum, ARGS, CARRY, MATERIAL_COUNT, MATERIAL_ORDER, ITEM_SET_CATEGORY_ORDER, ITEM_SET_NAME, JSON_OPTIONS};
+use ndarray::Array2;
+use std::{fmt::Write, fs::read_to_string, path::PathBuf};
 
+// Formats a cost vector with the material of every column, e.g. "380 bmat, 500 emat, 0 hemat, 0 rmat"
 pub fn format_cost_vector(cost_vector: &CostVec) -> String {
     let mut res: String = String::new();
-    for n in cost_vector {
-        let _ = write!(res, "{} ", n);
+    for (n, material) in cost_vector.iter().zip(MATERIAL_ORDER.iter()) {
+        let _ = write!(res, "{} {}, ", n, material.short_name());
+    }
+    res.truncate(res.len().saturating_sub(2));
+    return res;
+}
+
+// Formats a batch in the notation chosen by --output-batch-long
+pub fn format_batch(batch: &Batch) -> String {
+    return if ARGS.output_batch_long { format_batch_long(batch) } else { format_batch_short(batch) };
+}
+
+// Total cost of every queue in a batch
+pub fn batch_cost(batch: &Batch) -> CostVec {
+    return batch.iter().zip(ITEM_SET_CATEGORY_ORDER.iter())
+                .map(|(q, c)| c.queue_cost(q))
+                .fold(CostVec::zeros((1, *MATERIAL_COUNT)), |acc, cost| acc + cost);
+}
+
+// Formats the extra lines requested for every output batch, each starting on a new line
+pub fn format_batch_extras(batch: &Batch, cost: &CostVec) -> String {
+    return format_batch_extras_with_carry(batch, cost, &CARRY);
+}
+
+// Same as format_batch_extras, for batches that start with other materials in the truck than --carry
+pub fn format_batch_extras_with_carry(batch: &Batch, cost: &CostVec, carry: &CostVec) -> String {
+    let mut res: String = String::new();
+
+    if ARGS.loading_plan {
+        let _ = write!(res, "\n{}", LoadingPlan::new(cost));
+    }
+    if carry.iter().any(|x| *x != 0) {
+        let _ = write!(res, "\nCarry: {}", format_cost_vector(carry));
+    }
+    if ARGS.refinery {
+        // Carried over materials were already refined for an earlier batch
+        let _ = write!(res, "\n{}", Refinery::new(JSON_OPTIONS.refinery.as_ref()).format(&(cost - carry)));
+    }
+    if ARGS.simulate {
+        let _ = write!(res, "\n{}", QueueSimulation::new(batch, &item_production_times()));
+    }
+
+    if ARGS.expand {
+        let _ = write!(res, "\nExpansions: {}", count_expansions(batch));
+        if !ARGS.expand_count {
+            for expansion in expand_batch(batch, ARGS.expand_limit) {
+                let orders: Vec<String> = expansion.iter().map(|(name, q)| format!("{q} x {name}")).collect();
+                let _ = write!(res, "\nExpand: {}", orders.join(", "));
+            }
+        }
     }
-    res.pop();
     return res;
 }
 
+// Formats a single crate of an item in the notation chosen by --output-batch-long
+pub fn format_item(category: usize, item: usize) -> String {
+    if ARGS.output_batch_long {
+        return format!("1 x [{}]", ITEM_SET_CATEGORY_ORDER[category].item_order()[item].join(", "));
+    }
+    return format!("1{}{}", char::from_u32('A' as u32 + category as u32).unwrap(), item);
+}
+
 pub fn format_batch_long(batch: &Batch) -> String  {
     let mut res: String = String::new();
 
@@ -61,7 +118,7 @@ pub fn format_batch_short(batch: &Batch) -> String  {
 
 pub fn format_batch_groups(batch: &Batch) -> String {
     let mut res: String = String::new();
-    for i in 0..CATEGORY_COUNT {
+    for i in 0..ITEM_SET_CATEGORY_ORDER.len() {
         if i < batch.len() && batch[i].iter().any(|x| *x != 0) {
             let _ = write!(res, "1 ");
         } else {
@@ -70,4 +127,78 @@ pub fn format_batch_groups(batch: &Batch) -> String {
     }
     res.pop();
     return res;
+}
+
+// Parses per material amounts from entries such as "bmat=40", in MATERIAL_ORDER
+// Materials that are not listed are 0, `what` names the input in panics
+pub fn parse_material_amounts(entries: &[String], what: &str) -> Vec<u32> {
+    let mut res: Vec<u32> = vec![0; *MATERIAL_COUNT];
+    for entry in entries {
+        let entry = entry.trim();
+        if entry.is_empty() { continue; }
+
+        let (name, amount) = entry.split_once('=').unwrap_or_else(|| panic!("{what}: [{entry}] must be in the form \"<material>=<amount>\"."));
+        let idx = MATERIAL_ORDER.iter().position(|m| m.short_name() == name.trim().to_lowercase())
+                                .unwrap_or_else(|| panic!("{what}: [{}] is not a material of {}.", name.trim(), *ITEM_SET_NAME));
+        res[idx] = amount.trim().parse::<u32>().unwrap_or_else(|_| panic!("{what}: [{entry}] amount must be a number."));
+    }
+    return res;
+}
+
+// Reads one batch per line, with entries separated by commas
+// Lines labelled "Batch:" or "Batch 1:" (as in output files) have their label removed, any other labelled line is skipped
+pub fn read_batches(path: &PathBuf) -> Vec<Batch> {
+    let contents = read_to_string(path).unwrap_or_else(|e| panic!("Batches: could not read {}: {e}", path.display()));
+    let mut res: Vec<Batch> = Vec::new();
+    for line in contents.lines() {
+        let line = match line.split_once(':') {
+            Some((label, rest)) if label.trim_start().starts_with("Batch") => rest,
+            Some(_) => continue,
+            None => line,
+        };
+        if line.trim().is_empty() { continue; }
+
+        let entries: Vec<String> = line.split(',').map(String::from).collect();
+        res.push(parse_batch(&entries));
+    }
+    return res;
+}
+
+// Parses a batch from entries in short notation ("2B18", "2B18 1E1") or long notation ("2 x Mortar Shell", "2 x [Mortar Shell]")
+// Panics if an entry is not valid
+pub fn parse_batch(entries: &[String]) -> Batch {
+    let mut batch: Batch = ITEM_SET_CATEGORY_ORDER.iter().map(|c| Array2::zeros((1, usize::from(c.size())))).collect();
+
+    for entry in entries {
+        let entry = entry.trim();
+        if entry.is_empty() { continue; }
+
+        let positions: Vec<(usize, usize, OrderNum)> = if let Some(short) = entry.split_whitespace().map(parse_short_order).collect::<Option<Vec<_>>>() {
+            short
+        } else {
+            let (count, name) = entry.split_once('x').unwrap_or_else(|| panic!("Batch: [{entry}] must be in the form \"<count> x <item>\" or short notation."));
+            let count = count.trim().parse::<OrderNum>().unwrap_or_else(|_| panic!("Batch: [{entry}] count must be a number."));
+            let name = name.trim().trim_start_matches('[').trim_end_matches(']');
+            let (category, item) = find_item(name).unwrap_or_else(|| panic!("Batch: [{name}] is not an item in {}.", *ITEM_SET_NAME));
+            vec![(category, item, count)]
+        };
+
+        for (category, item, count) in positions {
+            if category >= batch.len() || item >= batch[category].len() {
+                panic!("Batch: [{entry}] is not an item in {}.", *ITEM_SET_NAME);
+            }
+            batch[category][[0, item]] += count;
+        }
+    }
+    return batch;
+}
+
+// Parses a single short notation order e.g. "2B18" -> (1, 18, 2)
+fn parse_short_order(order: &str) -> Option<(usize, usize, OrderNum)> {
+    let letter_idx = order.find(|c: char| c.is_ascii_uppercase())?;
+    let (count, rest) = order.split_at(letter_idx);
+    let mut rest_chars = rest.chars();
+    let category = rest_chars.next()? as usize - 'A' as usize;
+    let item = rest_chars.as_str().parse::<usize>().ok()?;
+    return Some((category, item, count.parse::<OrderNum>().ok()?));
 }
\ No newline at end of file
@@ -0,0 +1,101 @@
+pub mod model;
+pub mod cost_metric;
+pub mod helper;
+pub mod options;
+pub mod algo;
+
+use std::{env::current_dir, fs::create_dir_all, path::PathBuf, sync::Mutex};
+
+use crate::{cost_metric::CostMetric, helper::parse_material_amounts, model::crate_limits::CrateLimits, model::facility::Facility, model::item_set::{ItemSetCategory, ItemSetOption}, model::material::Material, options::{Cli, JsonOptions}};
+use clap::Parser;
+use ndarray::Array2;
+use lazy_static::lazy_static;
+
+pub type CostNum = u16;
+pub type OrderNum = u16;
+
+// 1 x MATERIAL_COUNT row vector, one column per material of the ItemSet
+pub type CostVec = Array2<CostNum>;
+// 1 x category.size() row vector
+pub type QueueVec = Array2<OrderNum>;
+// CATEGORY_COUNT x 
+pub type Batch = Vec<QueueVec>;
+
+pub const CATEGORY_COUNT: usize = 7;
+
+pub const TRUCK_SIZE: usize = 15;
+pub const TRUCK_SIZE_U16: u16 = 15;
+pub const MAX_ORDER: usize = 4;
+pub const MAX_ORDER_U16: u16 = 4;
+
+// Arguments set through set_args, taken when ARGS is first used
+static ARGS_OVERRIDE: Mutex<Option<Cli>> = Mutex::new(None);
+
+// Lets library users pick the item set and options without a command line, e.g. set_args(Cli::parse_from(["fmg", "-i", "warden"]))
+// Must be called before the first search, ARGS is parsed from the command line otherwise
+pub fn set_args(args: Cli) {
+    *ARGS_OVERRIDE.lock().unwrap() = Some(args);
+}
+
+// Unit tests share the process and so ARGS, every test that needs it sets the same warden arguments
+#[cfg(test)]
+pub(crate) fn set_test_args() {
+    set_args(Cli::parse_from(["fmg", "-i", "warden"]));
+}
+
+lazy_static! {
+
+    pub static ref ARGS: Cli = {
+        let args = ARGS_OVERRIDE.lock().unwrap().take().unwrap_or_else(Cli::parse);
+        args.check_metric();
+        return args;
+    };
+    pub static ref OUTPUT_PATH: PathBuf = {
+        let path = ARGS.path.clone().unwrap_or(current_dir().unwrap());
+        if !path.exists() {
+            let _ = create_dir_all(&path);
+        }
+        return path;
+    };
+
+    pub static ref JSON_OPTIONS: JsonOptions = {
+        let json_options = if ARGS.json_option_path.is_some() { JsonOptions::from_file(&ARGS.json_option_path.as_ref().unwrap()) } 
+                                                         else { JsonOptions::default() };
+        json_options.check_valid();
+        return json_options
+    };
+
+    pub static ref ITEM_SET_NAME: String = {
+        return match ARGS.item_set {
+            ItemSetOption::Warden => String::from("WardenItemSet"),
+            ItemSetOption::MaterialGroupedWarden => String::from("MaterialGroupedWardenItemSet"),
+        };
+    };
+
+    pub static ref ITEM_SET_CATEGORY_ORDER: Vec<Box<dyn ItemSetCategory>> = ARGS.item_set.item_set_category_order();
+
+    // Materials of the selected ItemSet, in the order of the cost vector columns
+    pub static ref MATERIAL_ORDER: Vec<Material> = ARGS.item_set.materials();
+    pub static ref MATERIAL_COUNT: usize = MATERIAL_ORDER.len();
+
+    // Materials already in the truck, every search starts from this cost
+    pub static ref CARRY: CostVec = {
+        let carry: Vec<CostNum> = parse_material_amounts(&ARGS.carry, "Carry").iter()
+                                                                              .map(|x| CostNum::try_from(*x).unwrap_or_else(|_| panic!("Carry: amounts must be <= {}.", CostNum::MAX)))
+                                                                              .collect();
+        let carry = CostVec::from_shape_vec((1, carry.len()), carry).unwrap();
+        if !CostMetric::Affordable.satisfies_metric(&carry) { panic!("Carry: does not fit in a truck."); }
+        return carry;
+    };
+
+    pub static ref FACILITY: Facility = {
+        let facilities = ARGS.item_set.facilities();
+        let facility = ARGS.facility.unwrap_or(facilities[0]);
+        if !facilities.contains(&facility) { panic!("{:?} can not be produced at a {:?}.", ARGS.item_set, facility); }
+        return facility;
+    };
+
+    // Per category and whole batch crate counts from --category-crates, --min-crates and the JSON options
+    pub static ref CRATE_LIMITS: CrateLimits = CrateLimits::from_options();
+}
+
@@ -1,64 +1,7 @@
-mod model;
-mod cost_metric;
-mod helper;
-mod options;
-mod algo;
+use std::time::Instant;
 
-use std::{env::current_dir, fs::create_dir_all, path::PathBuf, time::Instant};
-
-use crate::{algo::{n_batches::find_n_batches_with_metric, n_prime_groups::find_all_prime_groups_with_metric}, cost_metric::CostMetric, model::item_set::{ItemSetCategory, ItemSetOption}, model::material::Material, options::{Cli, JsonOptions}};
-use clap::Parser;
-use ndarray::Array2;
-use strum::IntoEnumIterator;
-use lazy_static::lazy_static;
-
-type CostNum = u16;
-type OrderNum = u16;
-
-// 1 x MATERIAL_COUNT row vector
-type CostVec = Array2<CostNum>;
-// 1 x category.size() row vector
-type QueueVec = Array2<OrderNum>;
-// CATEGORY_COUNT x 
-type Batch = Vec<QueueVec>;
-
-const CATEGORY_COUNT: usize = 7;
-const MATERIAL_COUNT: usize = 4;
-
-const TRUCK_SIZE: usize = 15;
-const TRUCK_SIZE_U16: u16 = 15;
-const MAX_ORDER: usize = 4;
-const MAX_ORDER_U16: u16 = 4;
-
-lazy_static! {
-    // [BMat, EMat, HEMat, RMat]
-    static ref MATERIAL_ORDER: Vec<Material> = Material::iter().collect();
-
-    pub static ref ARGS: Cli = Cli::parse();
-    pub static ref OUTPUT_PATH: PathBuf = {
-        let path = ARGS.path.clone().unwrap_or(current_dir().unwrap());
-        if !path.exists() {
-            let _ = create_dir_all(&path);
-        }
-        return path;
-    };
-
-    pub static ref JSON_OPTIONS: JsonOptions = {
-        let json_options = if ARGS.json_option_path.is_some() { JsonOptions::from_file(&ARGS.json_option_path.as_ref().unwrap()) } 
-                                                         else { JsonOptions::default() };
-        json_options.check_valid();
-        return json_options
-    };
-
-    pub static ref ITEM_SET_NAME: String = {
-        return match ARGS.item_set {
-            ItemSetOption::Warden => String::from("WardenItemSet"),
-            ItemSetOption::MaterialGroupedWarden => String::from("MaterialGroupedWardenItemSet"),
-        };
-    };
-
-    pub static ref ITEM_SET_CATEGORY_ORDER: Vec<Box<dyn ItemSetCategory>> = ARGS.item_set.item_set_category_order();
-}
+use fmg::{model::item_set::lint::lint_item_set, algo::{decompose::decompose_order_with_metric, exact::find_n_exact_cost_batches, fastest::find_n_fastest_batches_with_metric, n_batches::{complete_n_batches_with_metric, find_n_batches_with_metric}, near_miss::find_n_near_misses_with_metric, n_prime_groups::find_all_prime_groups_with_metric, pareto::find_n_pareto_fronts_with_metric, plan::plan_shift_with_metric, prioritize::find_n_weighted_batches_with_metric, sample::sample_n_batches_with_metric, schedule::schedule_with_objective, session::sequence_session_with_metric}, cost_metric::{count_stacks, CostMetric}, helper::{batch_cost, format_batch, format_batch_extras, format_cost_vector, parse_batch, parse_material_amounts}, model::income::Income, model::loading_plan::LoadingPlan, model::production::{item_production_times, QueueSimulation}, model::item_set::ItemSetOption, options::Command, ARGS, CARRY, TRUCK_SIZE_U16};
+use clap::ValueEnum;
 
 fn main() {
     let now = Instant::now();
@@ -66,7 +9,48 @@ fn main() {
     // find_n_groups_with_metric::<MaterialGroupedWardenItemSet>(2, CostMetric::PerfectlyStackable(TRUCK_SIZE_U16));
     // find_all_groups_with_metric::<MaterialGroupedWardenItemSet>(CostMetric::PerfectlyCrateable(TRUCK_SIZE_U16));
     // find_prime_n_groups_with_metric::<MaterialGroupedWardenItemSet>(2, CostMetric::Stackable);
-    find_n_batches_with_metric(2, CostMetric::PerfectlyStackable(TRUCK_SIZE_U16));
     // find_all_prime_groups_with_metric(CostMetric::Stackable);
+    match &ARGS.command {
+        Some(Command::Decompose { order_path, metric }) => decompose_order_with_metric(order_path, metric),
+        Some(Command::Complete { partial, n, metric }) => {
+            let partial = parse_batch(partial);
+            let n = n.unwrap_or(partial.iter().rposition(|q| q.iter().any(|x| *x != 0)).map_or(1, |c| c + 1));
+            complete_n_batches_with_metric(n, metric, &partial);
+        },
+        Some(Command::NearMiss { tolerance, n, metric }) => find_n_near_misses_with_metric(*n, metric, *tolerance),
+        Some(Command::Exact { exact_cost, tolerance, n, metric }) => find_n_exact_cost_batches(*n, metric, exact_cost, tolerance),
+        Some(Command::Pareto { objectives, fronts, n, metric }) => find_n_pareto_fronts_with_metric(*n, metric, objectives, *fronts),
+        Some(Command::Prioritize { weights_path, top, n, metric }) => find_n_weighted_batches_with_metric(*n, metric, weights_path, *top),
+        Some(Command::Plan { income, stock, hours, weights_path, n, metric }) => {
+            let income = Income::new(parse_material_amounts(income, "Income"), parse_material_amounts(stock, "Stock"));
+            plan_shift_with_metric(*n, metric, &income, *hours, weights_path.as_ref());
+        },
+        Some(Command::Sample { count, seed, max_attempts, n, metric }) => sample_n_batches_with_metric(*n, metric, *count, *seed, *max_attempts),
+        Some(Command::Fastest { top, n, metric }) => find_n_fastest_batches_with_metric(*n, metric, *top),
+        Some(Command::Session { batches, n, metric }) => sequence_session_with_metric(*n, metric, *batches),
+        Some(Command::Schedule { factories, batches_path, order_path, objective, metric }) => schedule_with_objective(batches_path.as_ref(), order_path.as_ref(), metric, *factories, *objective),
+        Some(Command::Inspect { batch }) => {
+            let batch = parse_batch(batch);
+            let cost = batch_cost(&batch) + &*CARRY;
+            // The loading plan and simulation are already part of the extras if requested
+            let loading_plan = if ARGS.loading_plan { String::new() } else { format!("\n{}", LoadingPlan::new(&cost)) };
+            let simulation = if ARGS.simulate { String::new() } else { format!("\n{}", QueueSimulation::new(&batch, &item_production_times())) };
+            println!("Batch : {}\nCost  : {}\nStacks: {}{}{}{}", format_batch(&batch), format_cost_vector(&cost), count_stacks(&cost), loading_plan, simulation, format_batch_extras(&batch, &cost));
+        },
+        Some(Command::LintItemSet { all }) => {
+            let options: Vec<ItemSetOption> = if *all { ItemSetOption::value_variants().to_vec() } else { vec![ARGS.item_set.clone()] };
+            let mut issue_count = 0;
+            for option in options {
+                let issues = lint_item_set(&option);
+                for issue in issues.iter() {
+                    println!("{:?}: {issue}", option);
+                }
+                if issues.is_empty() { println!("{:?}: no issues found", option); }
+                issue_count += issues.len();
+            }
+            if issue_count > 0 { std::process::exit(1); }
+        },
+        None => find_n_batches_with_metric(2, &CostMetric::PerfectlyStackable(TRUCK_SIZE_U16)),
+    }
     println!("Elapsed: {:.2?}", now.elapsed());
 }
\ No newline at end of file
@@ -0,0 +1,154 @@
+use crate::{model::item_set::item_crate_bounds, options::CrateBounds, OrderNum, ARGS, FACILITY, ITEM_SET_CATEGORY_ORDER, ITEM_SET_NAME, JSON_OPTIONS, TRUCK_SIZE_U16};
+
+// Crate counts every batch has to meet, from --category-crates / --min-crates and the JSON options
+#[derive(Debug, Clone)]
+pub struct CrateLimits {
+    // (min, max) crates of every category queue
+    pub category: Vec<(OrderNum, OrderNum)>,
+    // Fewest crates of a whole batch
+    pub min_crates: u16,
+}
+
+impl CrateLimits {
+    // Intersects the command line and JSON limits, panics if no batch can meet them
+    pub fn from_options() -> CrateLimits {
+        let mut category: Vec<(OrderNum, OrderNum)> = vec![(0, OrderNum::MAX); ITEM_SET_CATEGORY_ORDER.len()];
+        let cli_category_crates = parse_category_crates(&ARGS.category_crates);
+        for (c, bounds) in cli_category_crates.iter().chain(JSON_OPTIONS.category_crates.iter().flatten()) {
+            let (min, max) = &mut category[*c];
+            *min = (*min).max(bounds.min.unwrap_or(0));
+            *max = (*max).min(bounds.max.unwrap_or(OrderNum::MAX));
+        }
+        let min_crates = ARGS.min_crates.unwrap_or(0).max(JSON_OPTIONS.min_crates.unwrap_or(0));
+
+        let limits = CrateLimits { category, min_crates };
+        limits.check_feasible();
+        return limits;
+    }
+
+    // Whether a category queue of `count` crates is allowed
+    pub fn queue_allowed(&self, category: usize, count: OrderNum) -> bool {
+        let (min, max) = self.category[category];
+        return count >= min && count <= max;
+    }
+
+    // Panics if no batch can meet the limits
+    fn check_feasible(&self) {
+        let item_bounds = item_crate_bounds(JSON_OPTIONS.item_crates.as_ref());
+        let mut total_min: u16 = 0;
+        let mut total_max: u16 = 0;
+        for (c, (min, max)) in self.category.iter().enumerate() {
+            let name = ITEM_SET_CATEGORY_ORDER[c].to_string();
+            let item_min: u16 = item_bounds[c].iter().map(|(min, _)| min).sum();
+            if min > max { panic!("Crate limits: [{name}] needs at least {min} but at most {max} crates."); }
+            if *min > FACILITY.max_queue_crates() { panic!("Crate limits: [{name}] needs at least {min} crates, but a queue holds at most {}.", FACILITY.max_queue_crates()); }
+            if item_min > *max { panic!("Crate limits: [{name}] items need at least {item_min} crates, but the category allows at most {max}."); }
+            total_min += (*min).max(item_min);
+            total_max += (*max).min(FACILITY.max_queue_crates());
+        }
+
+        if total_min > TRUCK_SIZE_U16 { panic!("Crate limits: categories need at least {total_min} crates, but a truck holds {TRUCK_SIZE_U16}."); }
+        if self.min_crates > TRUCK_SIZE_U16 { panic!("Crate limits: batches need at least {} crates, but a truck holds {TRUCK_SIZE_U16}.", self.min_crates); }
+        if self.min_crates > total_max {
+            panic!("Crate limits: batches need at least {} crates, but the categories of {} hold at most {total_max}.", self.min_crates, *ITEM_SET_NAME);
+        }
+    }
+
+    // Panics if a search of the first n categories can never meet the limits
+    pub fn check_searched(&self, n: usize) {
+        if let Some(c) = self.category.iter().skip(n).position(|(min, _)| *min > 0) {
+            panic!("Crate limits: [{}] needs at least {} crates, but only the first {n} categories are searched.", ITEM_SET_CATEGORY_ORDER[c + n].to_string(), self.category[c + n].0);
+        }
+        let searched_max: u16 = self.category.iter().take(n).map(|(_, max)| (*max).min(FACILITY.max_queue_crates())).sum();
+        if self.min_crates > searched_max {
+            panic!("Crate limits: batches need at least {} crates, but the first {n} categories hold at most {searched_max}.", self.min_crates);
+        }
+    }
+}
+
+// Parses category crate limits such as "0=1..3", "Medical=..2" or "1=2..", categories are given by number or name
+pub fn parse_category_crates(entries: &[String]) -> Vec<(usize, CrateBounds)> {
+    let mut res: Vec<(usize, CrateBounds)> = Vec::new();
+    for entry in entries {
+        let entry = entry.trim();
+        if entry.is_empty() { continue; }
+
+        let (category, range) = entry.split_once('=').unwrap_or_else(|| panic!("Category crates: [{entry}] must be in the form \"<category>=<min>..<max>\"."));
+        let category = category.trim();
+        let c = category.parse::<usize>().ok()
+                        .or_else(|| ITEM_SET_CATEGORY_ORDER.iter().position(|c| c.to_string().eq_ignore_ascii_case(category)))
+                        .filter(|c| *c < ITEM_SET_CATEGORY_ORDER.len())
+                        .unwrap_or_else(|| panic!("Category crates: [{category}] is not a category of {}.", *ITEM_SET_NAME));
+
+        let (min, max) = range.split_once("..").unwrap_or_else(|| panic!("Category crates: [{entry}] must be in the form \"<category>=<min>..<max>\"."));
+        let parse = |x: &str| -> Option<OrderNum> {
+            let x = x.trim();
+            if x.is_empty() { return None; }
+            return Some(x.parse::<OrderNum>().unwrap_or_else(|_| panic!("Category crates: [{entry}] bounds must be numbers.")));
+        };
+        res.push((c, CrateBounds { min: parse(min), max: parse(max) }));
+    }
+    return res;
+}
+
+// res[c] sums values[c..]
+pub fn suffix_sums(values: &[u16]) -> Vec<u16> {
+    let mut res: Vec<u16> = vec![0; values.len() + 1];
+    for c in (0..values.len()).rev() {
+        res[c] = res[c + 1] + values[c];
+    }
+    return res;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::set_test_args;
+
+    #[test]
+    fn sums_every_suffix() {
+        assert_eq!(suffix_sums(&[4, 2, 0, 3]), vec![9, 5, 3, 3, 0]);
+        assert_eq!(suffix_sums(&[]), vec![0]);
+    }
+
+    #[test]
+    fn parses_categories_by_number_or_name() {
+        set_test_args();
+        let parsed: Vec<(usize, Option<OrderNum>, Option<OrderNum>)> = parse_category_crates(&[String::from("0=1..3"), String::from(" medical = ..2"), String::from("1=2.."), String::new()])
+                                                                           .into_iter().map(|(c, b)| (c, b.min, b.max)).collect();
+        assert_eq!(parsed, vec![(0, Some(1), Some(3)), (4, None, Some(2)), (1, Some(2), None)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "is not a category")]
+    fn rejects_unknown_categories() {
+        set_test_args();
+        parse_category_crates(&[String::from("7=1..3")]);
+    }
+
+    #[test]
+    fn allows_queues_within_the_category_bounds() {
+        let limits = CrateLimits { category: vec![(1, 3), (0, OrderNum::MAX)], min_crates: 0 };
+        assert!(!limits.queue_allowed(0, 0));
+        assert!(limits.queue_allowed(0, 1));
+        assert!(limits.queue_allowed(0, 3));
+        assert!(!limits.queue_allowed(0, 4));
+        assert!(limits.queue_allowed(1, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "only the first 1 categories are searched")]
+    fn rejects_searches_skipping_a_category_minimum() {
+        set_test_args();
+        let mut category = vec![(0, OrderNum::MAX); ITEM_SET_CATEGORY_ORDER.len()];
+        category[2] = (1, OrderNum::MAX);
+        CrateLimits { category, min_crates: 0 }.check_searched(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "the first 2 categories hold at most 8")]
+    fn rejects_searches_that_cannot_reach_the_fewest_crates() {
+        set_test_args();
+        CrateLimits { category: vec![(0, OrderNum::MAX); ITEM_SET_CATEGORY_ORDER.len()], min_crates: 9 }.check_searched(2);
+    }
+}
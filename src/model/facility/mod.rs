@@ -0,0 +1,117 @@
+use clap::ValueEnum;
+use ndarray::Array2;
+
+use crate::{CostNum, CostVec, OrderNum, QueueVec, MAX_ORDER_U16};
+
+// Mass Production Factory rules, as described in the Mass Production Factory article of the Foxhole wiki
+// Recheck them there after a game update that changes the MPF
+// Most crates of an item a single MPF order can hold
+const MPF_MAX_ORDER: u16 = 9;
+// Not a game rule: the MPF runs one order per category at a time, so a batch places a single order per category
+const MPF_MAX_QUEUE_ORDERS: usize = 1;
+// Every crate after the first in an order is discounted a further 10%, the discount stops growing at 50%
+const MPF_DISCOUNT_STEP_PERCENT: u32 = 10;
+const MPF_MAX_DISCOUNT_PERCENT: u32 = 50;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default, ValueEnum)]
+pub enum Facility {
+    #[default]
+    Factory,
+    MassProductionFactory,
+}
+
+impl Facility {
+    // Most crates a single item can be ordered
+    pub fn max_order(&self) -> u16 {
+        return match self {
+            Self::Factory => MAX_ORDER_U16,
+            Self::MassProductionFactory => MPF_MAX_ORDER,
+        }
+    }
+
+    // Most crates a category queue can hold
+    pub fn max_queue_crates(&self) -> u16 {
+        return match self {
+            Self::Factory => MAX_ORDER_U16,
+            Self::MassProductionFactory => MPF_MAX_ORDER,
+        }
+    }
+
+    // Most distinct items a category queue can hold
+    pub fn max_queue_orders(&self) -> usize {
+        return match self {
+            Self::Factory => usize::from(MAX_ORDER_U16),
+            Self::MassProductionFactory => MPF_MAX_QUEUE_ORDERS,
+        }
+    }
+
+    // Percentage of the full price paid for the nth (0 indexed) crate of an order
+    pub fn crate_price_percent(&self, n: u16) -> u32 {
+        return match self {
+            Self::Factory => 100,
+            Self::MassProductionFactory => 100 - (u32::from(n) * MPF_DISCOUNT_STEP_PERCENT).min(MPF_MAX_DISCOUNT_PERCENT),
+        }
+    }
+
+    // Cost of ordering `crates` crates of an item that costs `cost` for a single crate
+    // Only the total of the order is rounded, up to whole materials
+    pub fn order_cost(&self, cost: CostNum, crates: OrderNum) -> CostNum {
+        let percent: u32 = (0..crates).map(|n| self.crate_price_percent(n)).sum();
+        return (u32::from(cost) * percent).div_ceil(100) as CostNum;
+    }
+
+    // Cost of a queue given the single crate cost matrix of its category
+    pub fn queue_cost(&self, queue: &QueueVec, cost_matrix: &Array2<CostNum>) -> CostVec {
+        if *self == Self::Factory { return queue.dot(cost_matrix); }
+
+        let mut res = CostVec::zeros((1, cost_matrix.ncols()));
+        for (j, q) in queue.iter().enumerate() {
+            if *q == 0 { continue; }
+            for (idx, cost) in cost_matrix.row(j).iter().enumerate() {
+                res[[0, idx]] += self.order_cost(*cost, *q);
+            }
+        }
+        return res;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+
+    #[test]
+    fn mpf_discount_grows_per_crate_up_to_the_cap() {
+        let percents: Vec<u32> = (0..MPF_MAX_ORDER).map(|n| Facility::MassProductionFactory.crate_price_percent(n)).collect();
+        assert_eq!(percents, vec![100, 90, 80, 70, 60, 50, 50, 50, 50]);
+        assert!((0..MAX_ORDER_U16).all(|n| Facility::Factory.crate_price_percent(n) == 100));
+    }
+
+    #[test]
+    fn order_cost_rounds_the_order_total_up() {
+        let mpf = Facility::MassProductionFactory;
+        assert_eq!(mpf.order_cost(100, 0), 0);
+        assert_eq!(mpf.order_cost(100, 1), 100);
+        assert_eq!(mpf.order_cost(100, 2), 190);
+        // 15 * 1.9 = 28.5 and 25 * 2.7 = 67.5
+        assert_eq!(mpf.order_cost(15, 2), 29);
+        assert_eq!(mpf.order_cost(25, 3), 68);
+        // Every crate from the 6th on pays half price: 100 + 90 + 80 + 70 + 60 + 4 * 50
+        assert_eq!(mpf.order_cost(100, 9), 600);
+        assert_eq!(mpf.order_cost(5, 9), 30);
+        assert_eq!(Facility::Factory.order_cost(15, 4), 60);
+    }
+
+    #[test]
+    fn queue_cost_discounts_every_order_separately() {
+        let cost_matrix: Array2<CostNum> = Array::from_shape_vec((3, 2), vec![10, 0,
+                                                                             15, 5,
+                                                                             40, 20]).unwrap();
+        let queue: QueueVec = Array::from_shape_vec((1, 3), vec![0, 2, 1]).unwrap();
+
+        // 15 * 1.9 = 28.5 and 5 * 1.9 = 9.5 are rounded up per order before the single crate is added
+        assert_eq!(Facility::MassProductionFactory.queue_cost(&queue, &cost_matrix), Array::from_shape_vec((1, 2), vec![29 + 40, 10 + 20]).unwrap());
+        assert_eq!(Facility::Factory.queue_cost(&queue, &cost_matrix), Array::from_shape_vec((1, 2), vec![70, 30]).unwrap());
+    }
+}
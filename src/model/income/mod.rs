@@ -0,0 +1,77 @@
+use crate::{model::production::Seconds, CostVec};
+
+const SECONDS_PER_HOUR: u64 = 3600;
+
+// Materials on hand at the start of a shift and how fast more arrive, in MATERIAL_ORDER
+#[derive(Debug, Clone)]
+pub struct Income {
+    // Materials received per hour
+    pub rates: Vec<u32>,
+    // Materials on hand at the start
+    pub stock: Vec<u32>,
+}
+
+impl Income {
+    pub fn new(rates: Vec<u32>, stock: Vec<u32>) -> Income {
+        return Income { rates, stock };
+    }
+
+    // Materials on hand at time t after spending `spent`
+    // Income arrives continuously, rounded down to whole materials
+    pub fn available(&self, t: Seconds, spent: &[u64]) -> Vec<u64> {
+        return self.rates.iter().zip(self.stock.iter()).zip(spent)
+                   .map(|((rate, stock), spent)| u64::from(*stock) + u64::from(*rate) * u64::from(t) / SECONDS_PER_HOUR - spent)
+                   .collect();
+    }
+
+    // Earliest time from t on that a cost can be paid after spending `spent`, None if a material never arrives
+    pub fn earliest_affordable(&self, t: Seconds, spent: &[u64], cost: &CostVec) -> Option<Seconds> {
+        let mut res = t;
+        for (idx, x) in cost.iter().enumerate() {
+            let needed = (spent[idx] + u64::from(*x)).saturating_sub(u64::from(self.stock[idx]));
+            if needed == 0 { continue; }
+            if self.rates[idx] == 0 { return None; }
+
+            let arrives = (needed * SECONDS_PER_HOUR).div_ceil(u64::from(self.rates[idx]));
+            res = res.max(Seconds::try_from(arrives).ok()?);
+        }
+        return Some(res);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cost(amounts: [u16; 4]) -> CostVec {
+        return CostVec::from_shape_vec((1, 4), amounts.to_vec()).unwrap();
+    }
+
+    #[test]
+    fn waits_for_the_slowest_material() {
+        let income = Income::new(vec![400, 100, 0, 0], vec![50, 0, 0, 0]);
+        // Covered by the stock
+        assert_eq!(income.earliest_affordable(10, &[0; 4], &cost([50, 0, 0, 0])), Some(10));
+        // 50 more bmat at 400 per hour
+        assert_eq!(income.earliest_affordable(0, &[0; 4], &cost([100, 0, 0, 0])), Some(450));
+        // The emat takes longer than the bmat
+        assert_eq!(income.earliest_affordable(0, &[0; 4], &cost([100, 20, 0, 0])), Some(720));
+        // Never before t
+        assert_eq!(income.earliest_affordable(1000, &[0; 4], &cost([100, 20, 0, 0])), Some(1000));
+    }
+
+    #[test]
+    fn counts_what_was_already_spent() {
+        let income = Income::new(vec![400, 0, 0, 0], vec![50, 0, 0, 0]);
+        assert_eq!(income.earliest_affordable(0, &[50, 0, 0, 0], &cost([1, 0, 0, 0])), Some(9));
+        assert_eq!(income.available(9, &[50, 0, 0, 0]), vec![1, 0, 0, 0]);
+        assert_eq!(income.available(8, &[50, 0, 0, 0]), vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn never_affords_a_material_without_income() {
+        let income = Income::new(vec![400, 0, 0, 0], vec![0, 10, 0, 0]);
+        assert_eq!(income.earliest_affordable(0, &[0; 4], &cost([0, 10, 0, 0])), Some(0));
+        assert_eq!(income.earliest_affordable(0, &[0; 4], &cost([0, 11, 0, 0])), None);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{Batch, OrderNum, FACILITY, ITEM_SET_CATEGORY_ORDER};
+
+// A concrete order: Vec<(item name, crates)>
+pub type Expansion = Vec<(String, OrderNum)>;
+
+// Whether the crates of a group can be split over several of its items without changing the cost or validity of the queue
+// At a Mass Production Factory discounts are per order and a queue holds a single order, so a group goes to one item
+fn splits_groups() -> bool {
+    return FACILITY.crate_price_percent(1) == 100;
+}
+
+// Counts the concrete orders behind a batch
+// A group of k items ordered q times can be split in (k + q - 1) choose q ways, or k ways if groups are not split
+pub fn count_expansions(batch: &Batch) -> u128 {
+    return count_expansions_of(batch, &item_orders(), splits_groups());
+}
+
+fn count_expansions_of(batch: &Batch, item_orders: &[Vec<Vec<String>>], splits: bool) -> u128 {
+    let mut res: u128 = 1;
+    for (item_order, queue) in item_orders.iter().zip(batch.iter()) {
+        for (names, q) in item_order.iter().zip(queue.iter()) {
+            let (k, q) = (names.len() as u128, u128::from(*q));
+            if q == 0 { continue; }
+            if !splits {
+                res *= k;
+                continue;
+            }
+            // (k + q - 1) choose q, built up one term at a time so every step divides exactly
+            let mut ways: u128 = 1;
+            for j in 0..q {
+                ways = ways * (k + j) / (j + 1);
+            }
+            res *= ways;
+        }
+    }
+    return res;
+}
+
+// Lists the concrete orders behind a batch, up to limit
+// Every expansion costs the same as the batch since grouped items have identical costs
+pub fn expand_batch(batch: &Batch, limit: Option<usize>) -> Vec<Expansion> {
+    return expand_batch_of(batch, limit, &item_orders(), splits_groups());
+}
+
+fn expand_batch_of(batch: &Batch, limit: Option<usize>, item_orders: &[Vec<Vec<String>>], splits: bool) -> Vec<Expansion> {
+    let mut res: Vec<Expansion> = vec![Vec::new()];
+    for (item_order, queue) in item_orders.iter().zip(batch.iter()) {
+        for (names, q) in item_order.iter().zip(queue.iter()) {
+            if *q == 0 { continue; }
+
+            let group_splits: Vec<Expansion> = if splits { split_order(names, *q) } else { names.iter().map(|n| vec![(n.clone(), *q)]).collect() };
+            res = res.iter()
+                     .flat_map(|e| group_splits.iter().map(move |s| e.iter().cloned().chain(s.iter().cloned()).collect()))
+                     .take(limit.unwrap_or(usize::MAX))
+                     .collect();
+        }
+    }
+    return res;
+}
+
+// Item names of every category of the item set
+fn item_orders() -> Vec<Vec<Vec<String>>> {
+    return ITEM_SET_CATEGORY_ORDER.iter().map(|c| c.item_order()).collect();
+}
+
+// All ways to split count crates between names
+fn split_order(names: &[String], count: OrderNum) -> Vec<Expansion> {
+    if count == 0 { return vec![vec![]]; }
+    let Some((first, rest)) = names.split_first() else { return vec![]; };
+    if rest.is_empty() { return vec![vec![(first.clone(), count)]]; }
+
+    let mut res: Vec<Expansion> = Vec::new();
+    for n in (0..=count).rev() {
+        for mut split in split_order(rest, count - n) {
+            if n != 0 { split.insert(0, (first.clone(), n)); }
+            res.push(split);
+        }
+    }
+    return res;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{model::item_set::ItemSetOption, QueueVec};
+
+    #[test]
+    fn counts_every_expansion_it_lists() {
+        let item_orders: Vec<Vec<Vec<String>>> = ItemSetOption::MaterialGroupedWarden.item_set_category_order().iter().map(|c| c.item_order()).collect();
+        // Order 1 to 4 crates of every group in turn, with a second group in the same queue to multiply the ways
+        for (i, item_order) in item_orders.iter().enumerate() {
+            for j in 0..item_order.len() {
+                for q in 1..=4 {
+                    let mut batch: Batch = item_orders.iter().take(i + 1).map(|o| QueueVec::zeros((1, o.len()))).collect();
+                    batch[i][[0, j]] = q;
+                    batch[i][[0, (j + 1) % item_order.len()]] += 1;
+
+                    for splits in [true, false] {
+                        let expansions = expand_batch_of(&batch, None, &item_orders, splits);
+                        assert_eq!(count_expansions_of(&batch, &item_orders, splits), expansions.len() as u128, "category {i} group {j} x{q}, splits {splits}");
+                        assert_eq!(expansions.iter().collect::<HashSet<_>>().len(), expansions.len(), "category {i} group {j} x{q} has duplicate expansions");
+                        for e in expansions.iter() {
+                            assert_eq!(e.iter().map(|(_, n)| *n).sum::<OrderNum>(), batch[i].sum(), "category {i} group {j} x{q} expansion {e:?}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lists_up_to_the_limit() {
+        let item_orders = vec![vec![vec![String::from("a"), String::from("b"), String::from("c")]]];
+        let batch: Batch = vec![QueueVec::from_elem((1, 1), 3)];
+        assert_eq!(count_expansions_of(&batch, &item_orders, true), 10);
+        assert_eq!(expand_batch_of(&batch, None, &item_orders, true).len(), 10);
+        assert_eq!(expand_batch_of(&batch, Some(4), &item_orders, true).len(), 4);
+        assert_eq!(expand_batch_of(&batch, None, &item_orders, false), vec![vec![(String::from("a"), 3)], vec![(String::from("b"), 3)], vec![(String::from("c"), 3)]]);
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{model::{item_set::{ItemSetCategory, ItemSetOption}, material::Material}, OrderNum, CATEGORY_COUNT};
+
+// Returns every inconsistency in the definition of an ItemSet
+pub fn lint_item_set(option: &ItemSetOption) -> Vec<String> {
+    let categories = option.item_set_category_order();
+    let mut res = lint_categories(&categories);
+
+    if categories.is_empty() || categories.len() > CATEGORY_COUNT {
+        res.push(format!("{:?}: has {} categories, expected between [1 - {CATEGORY_COUNT}].", option, categories.len()));
+    }
+
+    // Every category must be costed in the materials of its ItemSet
+    let materials = option.materials();
+    for category in categories.iter() {
+        if category.materials() != materials {
+            res.push(format!("{}: is costed in {}, but {:?} is costed in {}.", category.to_string(), format_materials(&category.materials()), option, format_materials(&materials)));
+        }
+    }
+
+    // Every item of a grouped ItemSet must cost the same as it does in its base ItemSet
+    if let Some(base) = option.base_item_set() {
+        let base_costs = item_costs(&base.item_set_category_order());
+        let costs = item_costs(&categories);
+
+        for (name, (category, cost)) in costs.iter() {
+            match base_costs.get(name) {
+                Some((base_category, base_cost)) => {
+                    if base_category != category { res.push(format!("{category}: [{name}] is in {base_category} in {:?}.", base)); }
+                    if base_cost != cost { res.push(format!("{category}: [{name}] costs {:?}, but costs {:?} in {:?}.", cost, base_cost, base)); }
+                }
+                None => res.push(format!("{category}: [{name}] is not an item in {:?}.", base)),
+            }
+        }
+        for (name, (base_category, _)) in base_costs.iter() {
+            if !costs.contains_key(name) { res.push(format!("{base_category}: [{name}] from {:?} is missing.", base)); }
+        }
+    }
+    return res;
+}
+
+// Checks the sizes, cost matrices and item names of categories
+pub fn lint_categories(categories: &[Box<dyn ItemSetCategory>]) -> Vec<String> {
+    let mut res: Vec<String> = Vec::new();
+    let mut seen_names: HashMap<String, String> = HashMap::new();
+
+    for category in categories {
+        let category_name = category.to_string();
+        let size = usize::from(category.size());
+        let item_order = category.item_order();
+        let cost_matrix = category.cost_matrix();
+        let material_count = category.materials().len();
+
+        if material_count == 0 {
+            res.push(format!("{category_name}: has no materials."));
+            continue;
+        }
+        if item_order.len() != size {
+            res.push(format!("{category_name}: size() is {size}, but item_order() has {} items.", item_order.len()));
+        }
+        if cost_matrix.len() != size * material_count {
+            res.push(format!("{category_name}: cost_matrix() has {} values, expected size() * materials().len() = {}.", cost_matrix.len(), size * material_count));
+        }
+
+        for (j, names) in item_order.iter().enumerate() {
+            if names.is_empty() { res.push(format!("{category_name}: item {j} has no names.")); }
+            if cost_matrix.chunks(material_count).nth(j).is_some_and(|row| row.iter().all(|x| *x == 0)) {
+                res.push(format!("{category_name}: item {j} costs nothing."));
+            }
+
+            for name in names {
+                match seen_names.get(name) {
+                    Some(other) => res.push(format!("{category_name}: [{name}] is duplicated in {other}.")),
+                    None => { seen_names.insert(name.clone(), category_name.clone()); },
+                }
+            }
+        }
+    }
+    return res;
+}
+
+fn format_materials(materials: &[Material]) -> String {
+    return format!("[{}]", materials.iter().map(|m| m.short_name()).collect::<Vec<_>>().join(", "));
+}
+
+// Item name -> (category, cost row)
+fn item_costs(categories: &[Box<dyn ItemSetCategory>]) -> BTreeMap<String, (String, Vec<OrderNum>)> {
+    let mut res = BTreeMap::new();
+    for category in categories {
+        for (names, row) in category.item_order().iter().zip(category.cost_matrix().chunks(category.materials().len().max(1))) {
+            for name in names {
+                res.insert(name.clone(), (category.to_string(), row.to_vec()));
+            }
+        }
+    }
+    return res;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use clap::ValueEnum;
+    use ndarray::Array2;
+
+    use super::*;
+    use crate::model::item_set::warden_categories::WARDEN_MATERIALS;
+
+    #[test]
+    fn all_item_sets_are_consistent() {
+        for option in ItemSetOption::value_variants() {
+            let res = lint_item_set(option);
+            assert!(res.is_empty(), "{:?} has inconsistencies:\n{}", option, res.join("\n"));
+        }
+    }
+
+    struct BrokenCategory;
+
+    impl ItemSetCategory for BrokenCategory {
+        fn size(&self) -> u8 { 3 }
+        fn item_order(&self) -> Vec<Vec<String>> { vec![vec![String::from("Shovel")], vec![String::from("Shovel")]] }
+        fn materials(&self) -> Vec<Material> { WARDEN_MATERIALS.to_vec() }
+        fn cost_matrix(&self) -> Vec<OrderNum> { vec![200, 0, 0, 0, 0, 0, 0, 0] }
+        fn cost_matrix_ndarray(&self) -> Array2<OrderNum> { unreachable!() }
+    }
+
+    impl fmt::Display for BrokenCategory {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "Broken") }
+    }
+
+    #[test]
+    fn reports_every_inconsistency() {
+        let res = lint_categories(&[Box::new(BrokenCategory)]);
+        assert_eq!(res, vec![
+            String::from("Broken: size() is 3, but item_order() has 2 items."),
+            String::from("Broken: cost_matrix() has 8 values, expected size() * materials().len() = 12."),
+            String::from("Broken: item 1 costs nothing."),
+            String::from("Broken: [Shovel] is duplicated in Broken."),
+        ]);
+    }
+}
@@ -0,0 +1,69 @@
+use std::fmt;
+
+use crate::OrderNum;
+use crate::model::material::Material;
+use crate::model::item_set::ItemSetCategory;
+use ndarray::{Array, Array2};
+
+// A category where every item with an identical cost row is merged into one group
+// Groups keep the order in which their first item appears in the base category
+pub struct MaterialGroupedCategory {
+    name: String,
+    item_order: Vec<Vec<String>>,
+    cost_matrix: Vec<OrderNum>,
+    materials: Vec<Material>,
+}
+
+impl MaterialGroupedCategory {
+    pub fn new(base: &dyn ItemSetCategory) -> MaterialGroupedCategory {
+        let mut item_order: Vec<Vec<String>> = Vec::new();
+        let mut cost_rows: Vec<&[OrderNum]> = Vec::new();
+
+        let materials = base.materials();
+        let base_cost_matrix = base.cost_matrix();
+        for (names, row) in base.item_order().into_iter().zip(base_cost_matrix.chunks(materials.len())) {
+            match cost_rows.iter().position(|r| *r == row) {
+                Some(idx) => item_order[idx].extend(names),
+                None => {
+                    item_order.push(names);
+                    cost_rows.push(row);
+                }
+            }
+        }
+
+        return MaterialGroupedCategory {
+            name: base.to_string(),
+            cost_matrix: cost_rows.concat(),
+            item_order,
+            materials,
+        };
+    }
+}
+
+impl ItemSetCategory for MaterialGroupedCategory {
+    fn size(&self) -> u8 {
+        return self.item_order.len() as u8;
+    }
+
+    fn item_order(&self) -> Vec<Vec<String>> {
+        return self.item_order.clone();
+    }
+
+    fn cost_matrix(&self) -> Vec<OrderNum> {
+        return self.cost_matrix.clone();
+    }
+
+    fn materials(&self) -> Vec<Material> {
+        return self.materials.clone();
+    }
+
+    fn cost_matrix_ndarray(&self) -> Array2<OrderNum> {
+        return Array::from_shape_vec((usize::from(self.size()), self.materials.len()), self.cost_matrix()).unwrap();
+    }
+}
+
+impl fmt::Display for MaterialGroupedCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
@@ -1,12 +1,14 @@
 pub mod warden_categories;
-pub mod material_grouped_warden_categories;
+pub mod material_grouped;
+pub mod lint;
+pub mod expand;
 
-use std::{any::type_name, collections::VecDeque, fmt::Write as fmtWrite, fs::File, io::{BufWriter, Write as ioWrite}};
+use std::{any::type_name, collections::{HashMap, VecDeque}, fmt::Write as fmtWrite, fs::File, io::{BufWriter, Write as ioWrite}};
 use clap::ValueEnum;
 use ndarray::{Array, Array2};
 use strum::IntoEnumIterator;
 
-use crate::{cost_metric::CostMetric, model::item_set::{material_grouped_warden_categories::MaterialGroupedWardenCategories, warden_categories::WardenCategories}, CostVec, OrderNum, QueueVec, ITEM_SET_CATEGORY_ORDER, ITEM_SET_NAME, JSON_OPTIONS, MAX_ORDER, MAX_ORDER_U16, OUTPUT_PATH, TRUCK_SIZE_U16};
+use crate::{cost_metric::CostMetric, options::CrateBounds, model::{facility::Facility, item_set::{material_grouped::MaterialGroupedCategory, warden_categories::{WardenCategories, WARDEN_MATERIALS}}, material::Material}, CostVec, OrderNum, QueueVec, CRATE_LIMITS, FACILITY, ITEM_SET_CATEGORY_ORDER, ITEM_SET_NAME, JSON_OPTIONS, MAX_ORDER, MAX_ORDER_U16, OUTPUT_PATH, TRUCK_SIZE_U16};
 
 const DEFAULT_ORDER_RANGE: std::ops::RangeInclusive<u16> = 0..=MAX_ORDER_U16;
 
@@ -25,6 +27,28 @@ impl ItemSetOption {
                                              .max().unwrap();
     }
 
+    // Returns the ItemSet a grouped ItemSet was derived from
+    pub fn base_item_set(&self) -> Option<ItemSetOption> {
+        return match self {
+            ItemSetOption::Warden => None,
+            ItemSetOption::MaterialGroupedWarden => Some(ItemSetOption::Warden),
+        };
+    }
+
+    // Returns the materials every category of this ItemSet is costed in
+    pub fn materials(&self) -> Vec<Material> {
+        return match self {
+            ItemSetOption::Warden | ItemSetOption::MaterialGroupedWarden => WARDEN_MATERIALS.to_vec(),
+        };
+    }
+
+    // Returns the facilities that can produce this ItemSet, the first is the default
+    pub fn facilities(&self) -> Vec<Facility> {
+        return match self {
+            ItemSetOption::Warden | ItemSetOption::MaterialGroupedWarden => vec![Facility::Factory, Facility::MassProductionFactory],
+        };
+    }
+
     pub fn item_set_category_order(&self) -> Vec<Box<dyn ItemSetCategory>> {
         return match self {
             ItemSetOption::Warden => vec![
@@ -36,26 +60,27 @@ impl ItemSetOption {
                 Box::new(WardenCategories::Resources),
                 Box::new(WardenCategories::Uniforms),
             ],
-            ItemSetOption::MaterialGroupedWarden => vec![
-                Box::new(MaterialGroupedWardenCategories::SmallArms),
-                Box::new(MaterialGroupedWardenCategories::HeavyArms),
-                Box::new(MaterialGroupedWardenCategories::HeavyAmmunition),
-                Box::new(MaterialGroupedWardenCategories::Utility),
-                Box::new(MaterialGroupedWardenCategories::Medical),
-                Box::new(MaterialGroupedWardenCategories::Resources),
-                Box::new(MaterialGroupedWardenCategories::Uniforms),
-            ],
+            ItemSetOption::MaterialGroupedWarden => material_grouped(ItemSetOption::Warden.item_set_category_order()),
         };
     }
 }
 
+// Derives the material grouped variant of every category
+pub fn material_grouped(categories: Vec<Box<dyn ItemSetCategory>>) -> Vec<Box<dyn ItemSetCategory>> {
+    return categories.iter()
+                     .map(|c| Box::new(MaterialGroupedCategory::new(c.as_ref())) as Box<dyn ItemSetCategory>)
+                     .collect();
+}
+
 pub trait ItemSetCategory: ToString + Sync {
     // Returns the number of items in a category
     fn size(&self) -> u8;
     // Returns the names of items
     fn item_order(&self) -> Vec<Vec<String>>;
-    // Returns a self.size() x MATERIAL_COUNT matrix
-    // Retruns a largest_category_size() x MATERIAL_COUNT matrix
+    // Returns the material of every column of the cost matrix
+    fn materials(&self) -> Vec<Material>;
+    // Returns a self.size() x self.materials().len() matrix
+    // Retruns a largest_category_size() x self.materials().len() matrix
     // fn cost_matrix(&self) -> Array2<u16>;
     fn cost_matrix(&self) -> Vec<OrderNum>;
     fn cost_matrix_ndarray(&self) -> Array2<OrderNum>;
@@ -66,6 +91,10 @@ pub trait ItemSetCategory: ToString + Sync {
             .position(|c| c.to_string() == self_str)
             .expect("Category not found in ITEM_SET_CATEGORY_ORDER")
     }
+    // Returns the cost of a queue at the selected facility
+    fn queue_cost(&self, queue: &QueueVec) -> CostVec {
+        return FACILITY.queue_cost(queue, &self.cost_matrix_ndarray());
+    }
     // Generates all valid queues for this category
     // Returns Vec<(queue, cost, item_count)>
     fn generate_valid_queue_vec(&self) -> Vec<(QueueVec, CostVec, u16)> {
@@ -76,9 +105,20 @@ pub trait ItemSetCategory: ToString + Sync {
         let order_range: Vec<u16>;
         if JSON_OPTIONS.order_range.is_some() {
             order_range = JSON_OPTIONS.order_range.as_ref().unwrap().to_vec();
+        } else if *FACILITY != Facility::Factory {
+            order_range = (0..=FACILITY.max_order()).collect();
         } else {
             order_range = DEFAULT_ORDER_RANGE.collect();
         }
+
+        // Item crate constraints from the JSON options
+        let bounds: Vec<(OrderNum, OrderNum)> = if JSON_OPTIONS.item_crates.is_some() { item_crate_bounds(JSON_OPTIONS.item_crates.as_ref()).swap_remove(self.category_num()) }
+                                                else { vec![(0, OrderNum::MAX); usize::from(self.size())] };
+        let category_num = self.category_num();
+
+        if *FACILITY != Facility::Factory {
+            return self.generate_valid_queue_vec_with_pruning(&order_range, &bounds, category_num);
+        }
         
         while let Some(current) = queue.pop_front() {  
             let sum: u16 = current.iter().sum();
@@ -102,10 +142,51 @@ pub trait ItemSetCategory: ToString + Sync {
                         let c =  r.clone().dot(&self.cost_matrix_ndarray());
                         return (r, c, v.iter().sum::<u16>());
                     })
-                    .filter(|(_, c, s)| CostMetric::Affordable.satisfies_metric(c) && *s <= TRUCK_SIZE_U16)
+                    .filter(|(q, c, s)| CostMetric::Affordable.satisfies_metric(c) && *s <= TRUCK_SIZE_U16 && CRATE_LIMITS.queue_allowed(category_num, *s) &&
+                                        q.iter().zip(bounds.iter()).all(|(x, (min, max))| x >= min && x <= max))
                     .collect();
     }
 
+    // Generates all valid queues for facilities with larger orders, pruning as soon as a queue is no longer valid
+    // Returns Vec<(queue, cost, item_count)>
+    fn generate_valid_queue_vec_with_pruning(&self, order_range: &[u16], bounds: &[(OrderNum, OrderNum)], category_num: usize) -> Vec<(QueueVec, CostVec, u16)> {
+        let facility = *FACILITY;
+        let (category_min, category_max) = CRATE_LIMITS.category[category_num];
+        let cost_matrix = self.cost_matrix_ndarray();
+        let size = usize::from(self.size());
+        let mut res: Vec<(QueueVec, CostVec, u16)> = Vec::new();
+
+        // Stack for DFS: Vec<(queue, cost, item_count, order_count)>
+        let mut stack: Vec<(Vec<u16>, CostVec, u16, usize)> = vec![(Vec::new(), CostVec::zeros((1, cost_matrix.ncols())), 0, 0)];
+        while let Some((cur_queue, cur_cost, cur_item_count, cur_order_count)) = stack.pop() {
+            if cur_queue.len() == size {
+                if cur_item_count < category_min { continue; }
+                res.push((Array::from_shape_vec((1, size), cur_queue).unwrap(), cur_cost, cur_item_count));
+                continue;
+            }
+
+            let item = cur_queue.len();
+            let (min, max) = bounds[item];
+            for n in order_range.iter().rev() {
+                if *n < min || *n > max { continue; }
+                let new_item_count = cur_item_count + n;
+                let new_order_count = cur_order_count + usize::from(*n != 0);
+                if *n > facility.max_order() || new_item_count > facility.max_queue_crates() || new_item_count > TRUCK_SIZE_U16 || new_item_count > category_max || new_order_count > facility.max_queue_orders() { continue; }
+
+                let mut new_cost = cur_cost.clone();
+                for (idx, cost) in cost_matrix.row(item).iter().enumerate() {
+                    new_cost[[0, idx]] += facility.order_cost(*cost, *n);
+                }
+                if !CostMetric::Affordable.satisfies_metric(&new_cost) { continue; }
+
+                let mut new_queue = cur_queue.clone();
+                new_queue.push(*n);
+                stack.push((new_queue, new_cost, new_item_count, new_order_count));
+            }
+        }
+        return res;
+    }
+
     // Debug function that outputs all valid queues of a category to a file
     fn output_valid_queue_vec(&self) {
         let item_set_name = type_name::<Self>().split("::").last().unwrap();
@@ -132,6 +213,49 @@ pub trait ItemSetCategory: ToString + Sync {
     }
 }
 
+// (min, max) crates of every item of every category, intersecting every constraint on the same item
+// Items without a constraint are (0, OrderNum::MAX)
+pub fn item_crate_bounds(item_crates: Option<&HashMap<String, CrateBounds>>) -> Vec<Vec<(OrderNum, OrderNum)>> {
+    let mut res: Vec<Vec<(OrderNum, OrderNum)>> = ITEM_SET_CATEGORY_ORDER.iter().map(|c| vec![(0, OrderNum::MAX); usize::from(c.size())]).collect();
+    for (name, bounds) in item_crates.into_iter().flatten() {
+        let Some((c, i)) = find_item(name) else { continue; };
+        let (min, max) = &mut res[c][i];
+        *min = (*min).max(bounds.min.unwrap_or(0));
+        *max = (*max).min(bounds.max.unwrap_or(OrderNum::MAX));
+    }
+    return res;
+}
+
+// Crates the item and category minimums need from every category onwards, res[c] sums categories c..
+pub fn min_crates_from(bounds: &[Vec<(OrderNum, OrderNum)>]) -> Vec<u16> {
+    let mut res: Vec<u16> = vec![0; bounds.len() + 1];
+    for c in (0..bounds.len()).rev() {
+        res[c] = res[c + 1] + bounds[c].iter().map(|(min, _)| min).sum::<u16>().max(CRATE_LIMITS.category[c].0);
+    }
+    return res;
+}
+
+// Panics if an item needs crates from a category a search of the first n categories never orders from
+pub fn check_item_minimums_searched(bounds: &[Vec<(OrderNum, OrderNum)>], n: usize) {
+    for (c, category_bounds) in bounds.iter().enumerate().skip(n) {
+        if let Some(i) = category_bounds.iter().position(|(min, _)| *min > 0) {
+            panic!("Item crates: [{}] needs at least {} crates, but only the first {n} categories are searched.",
+                   ITEM_SET_CATEGORY_ORDER[c].item_order()[i].join(", "), category_bounds[i].0);
+        }
+    }
+}
+
+// Returns the (category, item) position of an item name in the current ItemSet
+// Grouped ItemSets return the position of the group containing the name
+pub fn find_item(name: &str) -> Option<(usize, usize)> {
+    for (i, category) in ITEM_SET_CATEGORY_ORDER.iter().enumerate() {
+        if let Some(j) = category.item_order().iter().position(|names| names.iter().any(|n| n.eq_ignore_ascii_case(name))) {
+            return Some((i, j));
+        }
+    }
+    return None;
+}
+
 // Outputs the legend file for an ItemSet
 pub fn output_legend_file() {
     let file_str: String = format!("{}_legend.txt", *ITEM_SET_NAME);
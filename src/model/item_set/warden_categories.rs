@@ -1,10 +1,14 @@
 use std::fmt;
 
-use crate::{OrderNum, MATERIAL_COUNT};
+use crate::OrderNum;
+use crate::model::material::Material;
 use crate::model::item_set::ItemSetCategory;
 use ndarray::{Array, Array2};
 use strum_macros::EnumIter;
 
+// [BMat, EMat, HEMat, RMat]
+pub const WARDEN_MATERIALS: [Material; 4] = [Material::BasicMaterial, Material::ExplosiveMaterial, Material::HeavyExplosiveMaterial, Material::RefinedMaterial];
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, EnumIter)]
 pub enum WardenCategories{
     SmallArms,
@@ -260,8 +264,12 @@ impl ItemSetCategory for WardenCategories {
         };
     }
 
+    fn materials(&self) -> Vec<Material> {
+        return WARDEN_MATERIALS.to_vec();
+    }
+
     fn cost_matrix_ndarray(&self) -> Array2<OrderNum> {
-        return Array::from_shape_vec((usize::from(self.size()), MATERIAL_COUNT), self.cost_matrix()).unwrap();
+        return Array::from_shape_vec((usize::from(self.size()), self.materials().len()), self.cost_matrix()).unwrap();
     }
 }
 
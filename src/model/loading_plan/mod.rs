@@ -0,0 +1,73 @@
+use std::fmt;
+
+use crate::{model::material::Material, CostNum, CostVec, MATERIAL_ORDER, TRUCK_SIZE_U16};
+
+// How much of a single material to pull for a batch
+#[derive(Debug, Clone)]
+pub struct MaterialLoad {
+    pub material: Material,
+    // Amount the batch costs
+    pub amount: CostNum,
+    // Number of truck slots the amount occupies
+    pub stacks: u16,
+    // Number of crates to pull to cover the amount
+    pub crates: u16,
+    // Amount left over in the last partially used crate
+    pub leftover: CostNum,
+}
+
+impl MaterialLoad {
+    pub fn new(material: Material, amount: CostNum) -> MaterialLoad {
+        let stacks = amount.div_ceil(material.stack_value());
+        let crates = amount.div_ceil(material.crate_value());
+        let leftover = crates * material.crate_value() - amount;
+        return MaterialLoad { material, amount, stacks, crates, leftover };
+    }
+}
+
+// Materials to pull for a batch and how to lay them out in a truck
+#[derive(Debug, Clone)]
+pub struct LoadingPlan {
+    pub loads: Vec<MaterialLoad>,
+}
+
+impl LoadingPlan {
+    pub fn new(cv: &CostVec) -> LoadingPlan {
+        let loads = cv.iter().enumerate()
+                      .filter(|(_, x)| **x != 0)
+                      .map(|(idx, x)| MaterialLoad::new(MATERIAL_ORDER[idx].clone(), *x))
+                      .collect();
+        return LoadingPlan { loads };
+    }
+
+    pub fn slot_count(&self) -> u16 {
+        return self.loads.iter().map(|l| l.stacks).sum();
+    }
+
+    // Contents of every truck slot in loading order: Vec<(material, amount)>
+    // Full stacks are loaded first, then the partial stack of each material
+    pub fn slots(&self) -> Vec<(Material, CostNum)> {
+        let mut res: Vec<(Material, CostNum)> = Vec::new();
+        for load in self.loads.iter() {
+            let stack_value = load.material.stack_value();
+            for _ in 0..load.amount / stack_value {
+                res.push((load.material.clone(), stack_value));
+            }
+        }
+        for load in self.loads.iter() {
+            let partial = load.amount % load.material.stack_value();
+            if partial != 0 { res.push((load.material.clone(), partial)); }
+        }
+        return res;
+    }
+}
+
+impl fmt::Display for LoadingPlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for load in self.loads.iter() {
+            writeln!(f, "Load : {} {} = {} stacks, {} crates, {} leftover", load.material.short_name(), load.amount, load.stacks, load.crates, load.leftover)?;
+        }
+        let slots: Vec<String> = self.slots().iter().map(|(m, x)| format!("[{} {}]", m.short_name(), x)).collect();
+        write!(f, "Slots: {}/{} {}", self.slot_count(), TRUCK_SIZE_U16, slots.join(" "))
+    }
+}
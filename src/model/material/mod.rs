@@ -1,5 +1,8 @@
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
+// Registry of every material a recipe can cost, an ItemSet picks the materials its cost matrices are made of
+// Only add a material along with an item set that costs in it, and with stack and crate values taken from the game
 #[derive(Debug, PartialEq, Eq, Hash, Clone, EnumIter)]
 pub enum Material {
     BasicMaterial,
@@ -9,6 +12,19 @@ pub enum Material {
 }
 
 impl Material {
+    pub fn short_name(&self) -> &'static str {
+        return match self {
+            Self::BasicMaterial => "bmat",
+            Self::ExplosiveMaterial => "emat",
+            Self::HeavyExplosiveMaterial => "hemat",
+            Self::RefinedMaterial => "rmat",
+        }
+    }
+
+    pub fn from_short_name(name: &str) -> Option<Material> {
+        return Material::iter().find(|m| m.short_name() == name.to_lowercase());
+    }
+
     pub fn stack_value(&self) -> u16 {
         return match self {
             Self::BasicMaterial => 100,
@@ -1,2 +1,8 @@
+pub mod crate_limits;
+pub mod facility;
+pub mod income;
 pub mod item_set;
-pub mod material;
\ No newline at end of file
+pub mod loading_plan;
+pub mod material;
+pub mod production;
+pub mod refinery;
\ No newline at end of file
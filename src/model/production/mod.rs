@@ -0,0 +1,132 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{helper::format_item, model::item_set::find_item, Batch, ITEM_SET_CATEGORY_ORDER, JSON_OPTIONS};
+
+// Without a time in the JSON options, a crate is estimated to take BASE_CRATE_SECONDS plus a second for every COST_PER_SECOND materials it costs
+// This is a rough guess, not game data, so estimated times are labelled as such and never used to rank batches
+const BASE_CRATE_SECONDS: Seconds = 10;
+const COST_PER_SECOND: Seconds = 10;
+
+pub type Seconds = u32;
+
+// Seconds to produce a single crate of every item, per category
+// Times are derived from item costs unless overridden by the JSON options, grouped items take the slowest override in their group
+pub fn item_production_times() -> Vec<Vec<Seconds>> {
+    let mut res: Vec<Vec<Seconds>> = ITEM_SET_CATEGORY_ORDER.iter()
+                                                            .map(|c| c.cost_matrix().chunks(c.materials().len())
+                                                                      .map(|row| BASE_CRATE_SECONDS + row.iter().map(|x| Seconds::from(*x)).sum::<Seconds>() / COST_PER_SECOND)
+                                                                      .collect())
+                                                            .collect();
+
+    let mut overrides: HashMap<(usize, usize), Seconds> = HashMap::new();
+    for (name, seconds) in JSON_OPTIONS.production_times.iter().flatten() {
+        // Names are checked when the JSON options are loaded
+        let item = find_item(name).unwrap();
+        let entry = overrides.entry(item).or_insert(*seconds);
+        *entry = (*entry).max(*seconds);
+    }
+    for ((category, item), seconds) in overrides {
+        res[category][item] = seconds;
+    }
+    return res;
+}
+
+// Whether the time of every item, per category, is estimated from its cost rather than set in the JSON options
+pub fn estimated_production_times() -> Vec<Vec<bool>> {
+    let mut res: Vec<Vec<bool>> = ITEM_SET_CATEGORY_ORDER.iter().map(|c| vec![true; usize::from(c.size())]).collect();
+    for name in JSON_OPTIONS.production_times.iter().flatten().map(|(name, _)| name) {
+        // Names are checked when the JSON options are loaded
+        let (category, item) = find_item(name).unwrap();
+        res[category][item] = false;
+    }
+    return res;
+}
+
+// Whether any crate of a batch takes an estimated time
+pub fn batch_times_estimated(batch: &Batch) -> bool {
+    return batch.iter().zip(estimated_production_times().iter()).any(|(q, e)| q.iter().zip(e).any(|(x, e)| *x != 0 && *e));
+}
+
+// Panics unless every item of the first n categories has a time in the JSON options
+pub fn require_category_production_times(n: usize, command: &str) {
+    let missing: Vec<(usize, usize)> = estimated_production_times().iter().take(n).enumerate()
+                                                                   .flat_map(|(c, e)| e.iter().enumerate().filter(|(_, e)| **e).map(move |(i, _)| (c, i)))
+                                                                   .collect();
+    require_production_times(&missing, command);
+}
+
+// Panics unless every item a batch orders has a time in the JSON options
+pub fn require_batch_production_times(batches: &[Batch], command: &str) {
+    let estimated = estimated_production_times();
+    let mut missing: Vec<(usize, usize)> = batches.iter()
+                                                  .flat_map(|b| b.iter().enumerate().flat_map(|(c, q)| q.iter().enumerate().filter(|(_, x)| **x != 0).map(move |(i, _)| (c, i))))
+                                                  .filter(|(c, i)| estimated[*c][*i])
+                                                  .collect();
+    missing.sort();
+    missing.dedup();
+    require_production_times(&missing, command);
+}
+
+// Estimated times are fine to look at, but batches are only ranked by times taken from the JSON options
+fn require_production_times(missing: &[(usize, usize)], command: &str) {
+    if missing.is_empty() { return; }
+    let names: Vec<String> = missing.iter().take(5).map(|(c, i)| ITEM_SET_CATEGORY_ORDER[*c].item_order()[*i].join(", ")).collect();
+    panic!("Production times: {command} ranks batches by production time, every item needs a time in the JSON options [production_times]. {} items have none, e.g. [{}].",
+           missing.len(), names.join("], ["));
+}
+
+// Seconds every category queue of a batch takes to produce all of its crates
+pub fn queue_durations(batch: &Batch, times: &[Vec<Seconds>]) -> Vec<Seconds> {
+    return batch.iter().enumerate()
+                .map(|(category, queue)| queue.iter().zip(times[category].iter()).map(|(q, t)| Seconds::from(*q) * t).sum())
+                .collect();
+}
+
+// When a single crate of a batch comes off the line
+#[derive(Debug, Clone)]
+pub struct CrateCompletion {
+    pub category: usize,
+    pub item: usize,
+    pub time: Seconds,
+}
+
+// Plays a batch out in the per-category queues of a facility
+// Every category queue runs at the same time, producing its crates one after the other in item order
+#[derive(Debug, Clone)]
+pub struct QueueSimulation {
+    // Sorted by completion time
+    pub completions: Vec<CrateCompletion>,
+    // Time until every crate is done and the truck can be collected
+    pub turnaround: Seconds,
+}
+
+impl QueueSimulation {
+    pub fn new(batch: &Batch, times: &[Vec<Seconds>]) -> QueueSimulation {
+        let mut completions: Vec<CrateCompletion> = Vec::new();
+        for (category, queue) in batch.iter().enumerate() {
+            let mut time: Seconds = 0;
+            for (item, q) in queue.iter().enumerate() {
+                for _ in 0..*q {
+                    time += times[category][item];
+                    completions.push(CrateCompletion { category, item, time });
+                }
+            }
+        }
+        completions.sort_by_key(|c| c.time);
+
+        let turnaround = completions.last().map_or(0, |c| c.time);
+        return QueueSimulation { completions, turnaround };
+    }
+}
+
+impl fmt::Display for QueueSimulation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let estimated = estimated_production_times();
+        for completion in self.completions.iter() {
+            let label = if estimated[completion.category][completion.item] { " (estimated)" } else { "" };
+            writeln!(f, "Crate: {} at {}s{label}", format_item(completion.category, completion.item), completion.time)?;
+        }
+        let label = if self.completions.iter().any(|c| estimated[c.category][c.item]) { " (estimated)" } else { "" };
+        write!(f, "Ready: {}s{label}", self.turnaround)
+    }
+}
@@ -0,0 +1,105 @@
+use std::{collections::BTreeMap, fmt};
+
+use strum_macros::EnumIter;
+
+use crate::{model::material::Material, options::RefineryOptions, CostVec, MATERIAL_ORDER};
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, EnumIter)]
+pub enum RawResource {
+    Salvage,
+    Components,
+    Sulfur,
+}
+
+impl RawResource {
+    pub fn short_name(&self) -> &'static str {
+        return match self {
+            Self::Salvage => "salvage",
+            Self::Components => "components",
+            Self::Sulfur => "sulfur",
+        }
+    }
+}
+
+impl Material {
+    // Raw resource a material is refined from
+    pub fn raw_resource(&self) -> RawResource {
+        return match self {
+            Self::BasicMaterial => RawResource::Salvage,
+            Self::ExplosiveMaterial => RawResource::Salvage,
+            Self::HeavyExplosiveMaterial => RawResource::Sulfur,
+            Self::RefinedMaterial => RawResource::Components,
+        }
+    }
+
+    // Default units of raw resource refined into one unit of material
+    pub fn default_refinery_ratio(&self) -> u32 {
+        return match self {
+            Self::BasicMaterial => 2,
+            Self::ExplosiveMaterial => 10,
+            Self::HeavyExplosiveMaterial => 10,
+            Self::RefinedMaterial => 20,
+        }
+    }
+}
+
+// Default raw resources carried per trip: a runner carries 5 slots of 100, a scroop truck 15 slots of 100
+const DEFAULT_TRIP_CAPACITIES: [(&str, u32); 2] = [("runner", 500), ("scroop", 1500)];
+
+// Conversion ratios from raw resources to materials and how much each kind of trip carries
+#[derive(Debug, Clone)]
+pub struct Refinery {
+    // Units of raw resource per unit of material, in MATERIAL_ORDER
+    pub ratios: Vec<u32>,
+    // Trip name -> raw resources carried per trip
+    pub trip_capacities: BTreeMap<String, u32>,
+}
+
+impl Refinery {
+    pub fn new(options: Option<&RefineryOptions>) -> Refinery {
+        let ratios = MATERIAL_ORDER.iter()
+                                   .map(|m| options.and_then(|o| o.ratios.as_ref())
+                                                   .and_then(|r| r.get(m.short_name()).copied())
+                                                   .unwrap_or(m.default_refinery_ratio()))
+                                   .collect();
+        let trip_capacities = match options.and_then(|o| o.trip_capacities.as_ref()) {
+            Some(capacities) => capacities.iter().map(|(k, v)| (k.clone(), *v)).collect(),
+            None => DEFAULT_TRIP_CAPACITIES.iter().map(|(k, v)| (String::from(*k), *v)).collect(),
+        };
+        return Refinery { ratios, trip_capacities };
+    }
+
+    // Raw resources needed to refine a cost vector
+    pub fn raw_resources(&self, cv: &CostVec) -> BTreeMap<RawResource, u32> {
+        let mut res: BTreeMap<RawResource, u32> = BTreeMap::new();
+        for (idx, x) in cv.iter().enumerate() {
+            if *x == 0 { continue; }
+            *res.entry(MATERIAL_ORDER[idx].raw_resource()).or_insert(0) += u32::from(*x) * self.ratios[idx];
+        }
+        return res;
+    }
+
+    // Trips of each kind needed to gather the raw resources for a cost vector
+    pub fn trips(&self, cv: &CostVec) -> BTreeMap<String, u32> {
+        let total: u32 = self.raw_resources(cv).values().sum();
+        return self.trip_capacities.iter().map(|(k, capacity)| (k.clone(), total.div_ceil(*capacity))).collect();
+    }
+
+    pub fn format(&self, cv: &CostVec) -> RefineryReport<'_> {
+        return RefineryReport { refinery: self, cost: cv.clone() };
+    }
+}
+
+// Raw resource totals and trips for a single cost vector
+pub struct RefineryReport<'a> {
+    refinery: &'a Refinery,
+    cost: CostVec,
+}
+
+impl fmt::Display for RefineryReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let raw: Vec<String> = self.refinery.raw_resources(&self.cost).iter().map(|(r, x)| format!("{} {}", r.short_name(), x)).collect();
+        let trips: Vec<String> = self.refinery.trips(&self.cost).iter().map(|(k, x)| format!("{} {}", k, x)).collect();
+        write!(f, "Raw  : {}\nTrips: {}", raw.join(", "), trips.join(", "))
+    }
+}
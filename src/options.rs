@@ -1,9 +1,9 @@
-use std::{collections::HashSet, fs::File, io::BufReader, path::PathBuf};
+use std::{collections::{HashMap, HashSet}, fs::File, io::BufReader, path::PathBuf};
 
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
 use serde::Deserialize;
 
-use crate::{model::item_set::ItemSetOption, OrderNum, CATEGORY_COUNT, ITEM_SET_CATEGORY_ORDER, MAX_ORDER, MAX_ORDER_U16};
+use crate::{algo::{pareto::Objective, schedule::ScheduleObjective}, model::facility::Facility, cost_metric::CostMetric, model::item_set::{find_item, item_crate_bounds, ItemSetOption}, model::material::Material, CostNum, OrderNum, ITEM_SET_CATEGORY_ORDER, ITEM_SET_NAME, FACILITY, TRUCK_SIZE_U16};
 
 #[derive(Parser, Debug)]
 pub struct Cli {
@@ -17,11 +17,265 @@ pub struct Cli {
     #[arg(short = 'l', long, default_value_t = false, requires = "output")]
     pub output_batch_long: bool,
 
+    /// List the concrete items behind every grouped batch
+    #[arg(short, long, default_value_t = false)]
+    pub expand: bool,
+    /// Maximum number of expansions listed per batch
+    #[arg(long, requires = "expand")]
+    pub expand_limit: Option<usize>,
+    /// Only count the expansions of each batch
+    #[arg(long, default_value_t = false, requires = "expand")]
+    pub expand_count: bool,
+
+    /// Show the materials to pull and the truck layout for every batch
+    #[arg(long, default_value_t = false)]
+    pub loading_plan: bool,
+    /// Show the raw resources and trips needed for every batch
+    #[arg(long, default_value_t = false)]
+    pub refinery: bool,
+    /// Materials left in the truck from a previous batch that every batch should use up, e.g. "bmat=40,emat=0"
+    #[arg(long, value_delimiter = ',')]
+    pub carry: Vec<String>,
+    /// Fewest and most crates of a category every batch orders, by category number or name, e.g. "0=1..3,Medical=..2"
+    #[arg(long, value_delimiter = ',')]
+    pub category_crates: Vec<String>,
+    /// Fewest crates every batch orders, e.g. 15 for a full truck
+    #[arg(long)]
+    pub min_crates: Option<u16>,
+    /// Show when every crate of a batch completes in the facility queues, times not in the JSON options are estimated
+    #[arg(long, default_value_t = false)]
+    pub simulate: bool,
+
     #[arg(short, long)]
     pub json_option_path: Option<PathBuf>,
 
     #[arg(short, long)]
     pub item_set: ItemSetOption,
+    /// Facility the batches are produced at, defaults to the first facility that can produce the item set
+    #[arg(short, long)]
+    pub facility: Option<Facility>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    // Exits with an invalid --metric error if a ratio does not fit the materials of the item set
+    pub fn check_metric(&self) {
+        let Some(metric) = self.command.as_ref().and_then(|c| c.metric()) else { return; };
+        if let Err(e) = metric.check_materials(&self.item_set.materials()) {
+            Cli::command().error(ErrorKind::ValueValidation, format!("invalid value '{metric}' for '--metric <METRIC>': {e}")).exit();
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Split an order list into truck-sized batches
+    Decompose {
+        /// JSON file mapping item names to crate counts
+        #[arg(long)]
+        order_path: PathBuf,
+        /// Metric each batch should satisfy
+        #[arg(short, long, default_value = "Stackable")]
+        metric: CostMetric,
+    },
+    /// Find every batch that completes a partial batch
+    Complete {
+        /// Partial batch in short notation ("2B18") or long notation ("2 x Mortar Shell"), comma separated
+        #[arg(long, value_delimiter = ',', required = true)]
+        partial: Vec<String>,
+        /// Number of categories to search [default: up to the last category in the partial batch]
+        #[arg(short)]
+        n: Option<usize>,
+        /// Metric each batch should satisfy
+        #[arg(short, long, default_value = "PerfectlyStackable(15)")]
+        metric: CostMetric,
+    },
+    /// Find batches within a tolerance of a stack boundary and suggest single item fixes
+    NearMiss {
+        /// Maximum distance of every material to a stack boundary
+        #[arg(short, long)]
+        tolerance: CostNum,
+        /// Number of categories to search
+        #[arg(short, default_value_t = 2)]
+        n: usize,
+        /// Metric a fixed batch should satisfy
+        #[arg(short, long, default_value = "PerfectlyStackable(15)")]
+        metric: CostMetric,
+    },
+    /// Find every batch that spends exactly the given materials
+    Exact {
+        /// Materials to spend, one amount per material of the item set in order, e.g. "1500,300,0,0"
+        #[arg(long, value_delimiter = ',', required = true)]
+        exact_cost: Vec<CostNum>,
+        /// Allowed difference from the target, a single amount for every material or one per material
+        #[arg(short, long, value_delimiter = ',')]
+        tolerance: Vec<CostNum>,
+        /// Number of categories to search
+        #[arg(short, default_value_t = 2)]
+        n: usize,
+        /// Metric each batch should satisfy
+        #[arg(short, long, default_value = "Affordable")]
+        metric: CostMetric,
+    },
+    /// Find the Pareto optimal batches across several objectives
+    Pareto {
+        /// Objectives to optimise, comma separated [min-stacks, max-stacks, min-crates, max-crates, min-categories, max-categories, min-<material>]
+        #[arg(long, value_delimiter = ',', required = true)]
+        objectives: Vec<Objective>,
+        /// Number of fronts to output
+        #[arg(short, long, default_value_t = 1)]
+        fronts: usize,
+        /// Number of categories to search
+        #[arg(short, default_value_t = 2)]
+        n: usize,
+        /// Metric each batch should satisfy
+        #[arg(short, long, default_value = "Affordable")]
+        metric: CostMetric,
+    },
+    /// Find the batches with the most weighted crates
+    Prioritize {
+        /// JSON file mapping item names to weights
+        #[arg(short, long)]
+        weights_path: PathBuf,
+        /// Number of batches to output
+        #[arg(short, long, default_value_t = 1)]
+        top: usize,
+        /// Number of categories to search [default: up to the last category with a weighted item]
+        #[arg(short)]
+        n: Option<usize>,
+        /// Metric each batch should satisfy
+        #[arg(short, long, default_value = "PerfectlyStackable(15)")]
+        metric: CostMetric,
+    },
+    /// Plan a shift of batches that never uses more materials than are on hand, needs production_times in the JSON options
+    Plan {
+        /// Materials received per hour, e.g. "bmat=1200,emat=300"
+        #[arg(long, value_delimiter = ',', required = true)]
+        income: Vec<String>,
+        /// Materials on hand at the start of the shift, e.g. "bmat=500"
+        #[arg(long, value_delimiter = ',')]
+        stock: Vec<String>,
+        /// Length of the shift in hours
+        #[arg(long, default_value_t = 8.0)]
+        hours: f64,
+        /// JSON file mapping item names to weights [default: every crate weighs 1]
+        #[arg(short, long)]
+        weights_path: Option<PathBuf>,
+        /// Number of categories to search
+        #[arg(short, default_value_t = 2)]
+        n: usize,
+        /// Metric each batch should satisfy
+        #[arg(short, long, default_value = "PerfectlyStackable(15)")]
+        metric: CostMetric,
+    },
+    /// Sample random batches uniformly
+    Sample {
+        /// Number of distinct batches to sample
+        #[arg(short, long, default_value_t = 20)]
+        count: usize,
+        /// Random seed, the same seed always gives the same sample
+        #[arg(short, long, default_value_t = 0)]
+        seed: u64,
+        /// Maximum number of random draws, including duplicates
+        #[arg(long, default_value_t = 1_000_000)]
+        max_attempts: u64,
+        /// Number of categories to search
+        #[arg(short, default_value_t = 2)]
+        n: usize,
+        /// Metric each batch should satisfy
+        #[arg(short, long, default_value = "PerfectlyStackable(15)")]
+        metric: CostMetric,
+    },
+    /// Find the batches that are ready for collection soonest, needs production_times in the JSON options
+    Fastest {
+        /// Number of batches to output
+        #[arg(short, long, default_value_t = 10)]
+        top: usize,
+        /// Number of categories to search
+        #[arg(short, default_value_t = 2)]
+        n: usize,
+        /// Metric each batch should satisfy
+        #[arg(short, long, default_value = "PerfectlyStackable(15)")]
+        metric: CostMetric,
+    },
+    /// Plan a session of batches where the partial stacks of each batch roll into the next, leaving as little as possible over at the end
+    Session {
+        /// Number of batches in the session
+        #[arg(short = 'k', long, default_value_t = 3)]
+        batches: usize,
+        /// Number of categories to search
+        #[arg(short, default_value_t = 2)]
+        n: usize,
+        /// Metric each batch should satisfy
+        #[arg(short, long, default_value = "Affordable")]
+        metric: CostMetric,
+    },
+    /// Assign batches to several factories and show the timeline of each factory
+    Schedule {
+        /// Number of factories running at once
+        #[arg(long, default_value_t = 2)]
+        factories: usize,
+        /// File with one batch per line, in short or long notation with comma separated entries
+        #[arg(long, required_unless_present = "order_path", conflicts_with = "order_path")]
+        batches_path: Option<PathBuf>,
+        /// JSON file mapping item names to crate counts, decomposed into batches first
+        #[arg(long)]
+        order_path: Option<PathBuf>,
+        /// What the schedule should minimise, makespan needs production_times in the JSON options
+        #[arg(long, default_value = "makespan")]
+        objective: ScheduleObjective,
+        /// Metric each batch of a decomposed order should satisfy
+        #[arg(short, long, default_value = "Stackable")]
+        metric: CostMetric,
+    },
+    /// Show the cost and details of a single batch
+    Inspect {
+        /// Batch in short notation ("2B18") or long notation ("2 x Mortar Shell"), comma separated
+        #[arg(long, value_delimiter = ',', required = true)]
+        batch: Vec<String>,
+    },
+    /// Check item set definitions for inconsistencies
+    LintItemSet {
+        /// Check every item set instead of the selected one
+        #[arg(short, long, default_value_t = false)]
+        all: bool,
+    },
+}
+
+impl Command {
+    // Metric of the command, if it takes one
+    pub fn metric(&self) -> Option<&CostMetric> {
+        return match self {
+            Self::Decompose { metric, .. } | Self::Complete { metric, .. } | Self::NearMiss { metric, .. } | Self::Pareto { metric, .. } |
+            Self::Prioritize { metric, .. } | Self::Plan { metric, .. } | Self::Sample { metric, .. } | Self::Fastest { metric, .. } |
+            Self::Session { metric, .. } | Self::Schedule { metric, .. } | Self::Exact { metric, .. } => Some(metric),
+            Self::Inspect { .. } | Self::LintItemSet { .. } => None,
+        };
+    }
+}
+
+// Item name -> number of crates
+pub type JsonOrder = HashMap<String, OrderNum>;
+
+// Item name -> weight
+pub type JsonWeights = HashMap<String, f64>;
+
+pub fn read_json_weights(json_path: &PathBuf) -> JsonWeights {
+    let file = File::open(json_path).unwrap();
+    let reader = BufReader::new(file);
+
+    let json_weights: JsonWeights = serde_json::from_reader(reader).unwrap();
+    return json_weights;
+}
+
+pub fn read_json_order(json_path: &PathBuf) -> JsonOrder {
+    let file = File::open(json_path).unwrap();
+    let reader = BufReader::new(file);
+
+    let json_order: JsonOrder = serde_json::from_reader(reader).unwrap();
+    return json_order;
 }
 
 type CategoryNum = usize;
@@ -44,33 +298,35 @@ impl OptionChoice {
     fn check_valid(&self) -> bool {
         match self {
             OptionChoice::Category(c) => {
-                if *c >= CATEGORY_COUNT { panic!("JSON Options: [Category] must be between [0 - {CATEGORY_COUNT})."); }
+                if *c >= ITEM_SET_CATEGORY_ORDER.len() { panic!("JSON Options: [Category] must be between [0 - {}).", ITEM_SET_CATEGORY_ORDER.len()); }
             }
             OptionChoice::ItemOrders((c, items)) => {
-                if *c >= CATEGORY_COUNT { panic!("JSON Options: [ItemsOrders] category must be between [0 - {CATEGORY_COUNT})."); }
+                if *c >= ITEM_SET_CATEGORY_ORDER.len() { panic!("JSON Options: [ItemsOrders] category must be between [0 - {}).", ITEM_SET_CATEGORY_ORDER.len()); }
                 for (item, range) in items {
                     if *item >= usize::from(ITEM_SET_CATEGORY_ORDER[*c].size()) {
                         panic!("JSON Options: [ItemsOrders] item must be between [0 - {}) for category {}.", ITEM_SET_CATEGORY_ORDER[*c].size(), *c);
                     }
 
                     for r in range {
-                        if *r > MAX_ORDER_U16 {
-                            panic!("Json Options: [ItemsOrders] item range must be between [0 - {MAX_ORDER}].")
+                        if *r > FACILITY.max_order() {
+                            panic!("Json Options: [ItemsOrders] item range must be between [0 - {}].", FACILITY.max_order())
                         }
                     }
                 }
             }
             OptionChoice::Queue((c, items)) => {
+                if *c >= ITEM_SET_CATEGORY_ORDER.len() { panic!("JSON Options: [Queue] category must be between [0 - {}).", ITEM_SET_CATEGORY_ORDER.len()); }
                 let category_size = ITEM_SET_CATEGORY_ORDER[*c].size();
-
-                if *c >= CATEGORY_COUNT { panic!("JSON Options: [Queue] category must be between [0 - {CATEGORY_COUNT})."); }
                 if items.len() != usize::from(category_size) { panic!("JSON Options: [Queue] queue size must be {category_size}.")}
-                if items.iter().sum::<u16>() > MAX_ORDER_U16 {
-                    panic!("JSON Options: [Queue] orders must sum <= {MAX_ORDER}");
+                if items.iter().sum::<u16>() > FACILITY.max_queue_crates() {
+                    panic!("JSON Options: [Queue] orders must sum <= {}", FACILITY.max_queue_crates());
+                }
+                if items.iter().filter(|x| **x != 0).count() > FACILITY.max_queue_orders() {
+                    panic!("JSON Options: [Queue] must order <= {} items", FACILITY.max_queue_orders());
                 }
                 for item in items {
-                    if *item > MAX_ORDER_U16 {
-                        panic!("JSON Options: [Queue] order must be between [0 - {MAX_ORDER}).");
+                    if *item > FACILITY.max_order() {
+                        panic!("JSON Options: [Queue] order must be between [0 - {}].", FACILITY.max_order());
                     }
                 }
             }
@@ -87,6 +343,40 @@ impl OptionChoice {
     }
 }
 
+// Panics if no batch can meet the item crate bounds
+fn check_item_crates_feasible(bounds: &[Vec<(OrderNum, OrderNum)>], order_range: Option<&OrderRange>) {
+    let orders: Vec<OrderNum> = order_range.cloned().unwrap_or((0..=FACILITY.max_order()).collect());
+    let mut total_min: u16 = 0;
+    for (c, category_bounds) in bounds.iter().enumerate() {
+        let item_order = ITEM_SET_CATEGORY_ORDER[c].item_order();
+        for (i, (min, max)) in category_bounds.iter().enumerate() {
+            let name = item_order[i].join(", ");
+            if min > max { panic!("JSON Options: [Item Crates] [{name}] needs at least {min} but at most {max} crates."); }
+            if (*min, *max) != (0, OrderNum::MAX) && !orders.iter().any(|o| o >= min && o <= max) {
+                let needs = if *max == OrderNum::MAX { format!("at least {min}") } else { format!("between {min} and {max}") };
+                panic!("JSON Options: [Item Crates] [{name}] needs {needs} crates, but orders can only be {orders:?}.");
+            }
+        }
+
+        let category_min: u16 = category_bounds.iter().map(|(min, _)| min).sum();
+        let category_orders = category_bounds.iter().filter(|(min, _)| *min > 0).count();
+        if category_min > FACILITY.max_queue_crates() || category_orders > FACILITY.max_queue_orders() {
+            panic!("JSON Options: [Item Crates] category {c} needs {category_min} crates of {category_orders} items, but a queue holds at most {} crates of {} items.",
+                   FACILITY.max_queue_crates(), FACILITY.max_queue_orders());
+        }
+        total_min += category_min;
+    }
+    if total_min > TRUCK_SIZE_U16 { panic!("JSON Options: [Item Crates] needs at least {total_min} crates, but a truck holds {TRUCK_SIZE_U16}."); }
+}
+
+#[derive(Deserialize, Default, Debug)]
+pub struct RefineryOptions {
+    // Units of raw resource refined into one unit of material, keyed by material short name e.g. "bmat"
+    pub ratios: Option<HashMap<String, u32>>,
+    // Raw resources carried per trip, keyed by trip name e.g. "runner"
+    pub trip_capacities: Option<HashMap<String, u32>>,
+}
+
 #[derive(Deserialize, Default, Debug)]
 pub struct JsonOptions {
     // Default order range
@@ -103,7 +393,24 @@ pub struct JsonOptions {
     //                                                  -> item must have a single order
     //                                                  -> category queue must still be valid
     //     * Queues       (category, [queue])           -> specific category queue will be included in all batches/groups
-    pub whitelist: Option<Vec<OptionChoice>>
+    pub whitelist: Option<Vec<OptionChoice>>,
+    // Refinery conversion ratios and trip capacities used to trace batches back to raw resources
+    pub refinery: Option<RefineryOptions>,
+    // Seconds to produce a single crate, keyed by item name, overrides the time derived from the item cost
+    pub production_times: Option<HashMap<String, u32>>,
+    // Fewest and most crates of an item every batch orders, keyed by item name e.g. {"Bandages": {"min": 2}, "Mortar Shell": {"min": 1, "max": 3}}
+    // Items grouped with other items are rejected, a bound on the group would not bound the item
+    pub item_crates: Option<HashMap<String, CrateBounds>>,
+    // Fewest and most crates of a category every batch orders, e.g. [[0, {"min": 1, "max": 3}], [4, {"max": 2}]]
+    pub category_crates: Option<Vec<(CategoryNum, CrateBounds)>>,
+    // Fewest crates every batch orders, e.g. 15 for a full truck
+    pub min_crates: Option<u16>,
+}
+
+#[derive(Deserialize, Default, Debug, Clone, Copy)]
+pub struct CrateBounds {
+    pub min: Option<OrderNum>,
+    pub max: Option<OrderNum>,
 }
 
 impl JsonOptions {
@@ -121,7 +428,7 @@ impl JsonOptions {
         let mut order_range_values: HashSet<OrderNum> = HashSet::new();
         if self.order_range.is_some() {
             for order in self.order_range.as_ref().unwrap().iter() {
-                if *order > MAX_ORDER_U16 { panic!("JSON Options: [Order Range] value must be between [0 - {MAX_ORDER}].") }
+                if *order > FACILITY.max_order() { panic!("JSON Options: [Order Range] value must be between [0 - {}].", FACILITY.max_order()) }
                 if order_range_values.contains(&order) { panic!("JSON Options: [Order Range] value must be unique.") }
                 order_range_values.insert(*order);
             }
@@ -182,6 +489,40 @@ impl JsonOptions {
             }
         }
 
+        if let Some(refinery) = self.refinery.as_ref() {
+            for (material, ratio) in refinery.ratios.iter().flatten() {
+                if Material::from_short_name(material).is_none() { panic!("JSON Options: [Refinery] [{material}] is not a material."); }
+                if *ratio == 0 { panic!("JSON Options: [Refinery] ratios must be > 0."); }
+            }
+            for capacity in refinery.trip_capacities.iter().flat_map(|c| c.values()) {
+                if *capacity == 0 { panic!("JSON Options: [Refinery] trip capacities must be > 0."); }
+            }
+        }
+
+        for (name, seconds) in self.production_times.iter().flatten() {
+            if find_item(name).is_none() { panic!("JSON Options: [Production Times] [{name}] is not an item in {}.", *ITEM_SET_NAME); }
+            if *seconds == 0 { panic!("JSON Options: [Production Times] times must be > 0."); }
+        }
+
+        if let Some(item_crates) = self.item_crates.as_ref() {
+            for (name, bounds) in item_crates.iter() {
+                let Some((c, i)) = find_item(name) else { panic!("JSON Options: [Item Crates] [{name}] is not an item in {}.", *ITEM_SET_NAME); };
+                let group = &ITEM_SET_CATEGORY_ORDER[c].item_order()[i];
+                if group.len() > 1 {
+                    let others: Vec<&str> = group.iter().filter(|n| !n.eq_ignore_ascii_case(name)).map(|n| n.as_str()).collect();
+                    panic!("JSON Options: [Item Crates] [{name}] is grouped with [{}] in {}, only items that are not grouped can be bounded.", others.join(", "), *ITEM_SET_NAME);
+                }
+                if bounds.min.unwrap_or(0) > bounds.max.unwrap_or(OrderNum::MAX) { panic!("JSON Options: [Item Crates] [{name}] min must be <= max."); }
+            }
+            check_item_crates_feasible(&item_crate_bounds(Some(item_crates)), self.order_range.as_ref());
+        }
+
+        for (category, bounds) in self.category_crates.iter().flatten() {
+            if *category >= ITEM_SET_CATEGORY_ORDER.len() { panic!("JSON Options: [Category Crates] category must be between [0 - {}].", ITEM_SET_CATEGORY_ORDER.len() - 1); }
+            if bounds.min.unwrap_or(0) > bounds.max.unwrap_or(OrderNum::MAX) { panic!("JSON Options: [Category Crates] [{category}] min must be <= max."); }
+        }
+        if self.min_crates.is_some_and(|min| min > TRUCK_SIZE_U16) { panic!("JSON Options: [Min Crates] must be <= {TRUCK_SIZE_U16}."); }
+
         return true;
     }
 }
\ No newline at end of file
@@ -0,0 +1,37 @@
+use std::collections::BTreeSet;
+
+use clap::Parser;
+use fmg::{algo::{n_batches::visit_n_batches_with_metric, sample::sample_n_batches}, cost_metric::{CostMetric, Metric}, options::Cli, set_args, Batch, CostVec, ITEM_SET_CATEGORY_ORDER};
+
+// Batches as plain numbers, so they can be compared as sets
+fn key(batch: &Batch) -> Vec<Vec<u16>> {
+    return batch.iter().map(|q| q.iter().copied().collect()).collect();
+}
+
+// The crate limits are global, so they get a process of their own
+#[test]
+fn searches_prune_exactly_the_batches_outside_the_crate_limits() {
+    set_args(Cli::parse_from(["fmg", "-i", "warden", "--min-crates", "4", "--category-crates", "0=..1,1=2.."]));
+    let metric = CostMetric::Stackable;
+
+    // Every pair of queues of the first two categories, the queues already meet the category limits
+    let mut expected: BTreeSet<Vec<Vec<u16>>> = BTreeSet::new();
+    let first = ITEM_SET_CATEGORY_ORDER[0].generate_valid_queue_vec();
+    let second = ITEM_SET_CATEGORY_ORDER[1].generate_valid_queue_vec();
+    assert!(first.iter().all(|(_, _, count)| *count <= 1) && second.iter().all(|(_, _, count)| *count >= 2));
+    for (q0, c0, n0) in first.iter() {
+        for (q1, c1, n1) in second.iter() {
+            let cost: CostVec = c0 + c1;
+            if n0 + n1 >= 4 && CostMetric::Affordable.satisfies(&cost) && metric.satisfies(&cost) {
+                expected.insert(key(&vec![q0.clone(), q1.clone()]));
+            }
+        }
+    }
+    assert!(!expected.is_empty());
+
+    let mut visited: BTreeSet<Vec<Vec<u16>>> = BTreeSet::new();
+    visit_n_batches_with_metric(2, &metric, &Vec::new(), &CostVec::zeros((1, 4)), |batch, _| assert!(visited.insert(key(batch))));
+    assert_eq!(visited, expected);
+
+    assert_eq!(sample_n_batches(2, &metric, 1, 0, 1).1, expected.len() as u128);
+}